@@ -0,0 +1,472 @@
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use rand_core::OsRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::bullerproof::InnerProductProof;
+use crate::errors::{DecodeError, ProofError};
+use crate::generator::{n2_gen, powers_gen, GlobalPoints};
+use crate::operations::{hadamard_multiply, inner_product, invert, points_hadamard_multiply, scalarize, vec_scalar_mul, vector_add, vector_sub};
+use crate::prover::{ASVcommitment, Opening};
+use crate::rangeproof::PROTOCOL_VERSION;
+use crate::serialization::{write_points, write_scalars, Reader};
+use crate::text::{armor_plain, dearmor_plain, from_hex, to_hex};
+use crate::transcript::Transcript;
+use crate::verifier::VerifierKey;
+#[cfg(feature = "serde")]
+use crate::text::{hex_point, hex_scalar};
+
+// Mode byte of an aggregated proof, after those of RangeProofVariant.
+pub(crate) const AGGREGATED_MODE: u8 = 3;
+
+// Range proof over several values at once, where value j only pays for its own ranges[j] bits.
+// The bit vectors are laid out back to back and padded with zero bits to a power of two.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AggregatedRangeProof {
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_widths"))]
+    ranges: Vec<usize>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_a: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_s: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_t1: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_t2: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    tu: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_lr: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_t: Scalar,
+    ipp: InnerProductProof,
+}
+
+impl AggregatedRangeProof {
+    pub fn prove(transcript: &mut Transcript, points: &GlobalPoints, openings: &[Opening], ranges: &[usize]) -> Result<(Self, Vec<RistrettoPoint>), ProofError> {
+        let count = check_ranges(points, openings.len(), ranges)?;
+        if openings.iter().zip(ranges.iter()).any(|(x, &n)| n < 64 && x.value() >> n != 0) {
+            return Err(ProofError::ValueOutOfRange);
+        }
+        let commits: Vec<RistrettoPoint> = openings.iter().map(|x| x.commit(points)).collect();
+        let g_basis = points.g_basis()[..count].to_vec();
+        let h_basis = points.h_basis()[..count].to_vec();
+        append_statement(transcript, &commits, ranges);
+
+        let mut al = vec![];
+        for (opening, &range) in openings.iter().zip(ranges.iter()) {
+            let [mut bits, _] = ASVcommitment::compute_al_ar(opening.value(), range);
+            al.extend(scalarize(&mut bits));
+        }
+        al.resize(count, Scalar::ZERO);
+        let ar = vector_sub(&al, &vec![Scalar::ONE; count]);
+
+        let mut rng = OsRng;
+        let [salt_alpha, salt_beta, salt_tau1, salt_tau2] = [(); 4].map(|_| Scalar::random(&mut rng));
+        let sl: Vec<Scalar> = (0..count).map(|_| Scalar::random(&mut rng)).collect();
+        let sr: Vec<Scalar> = (0..count).map(|_| Scalar::random(&mut rng)).collect();
+        let commit_a = points.vector_commit([&al, &ar], &salt_alpha);
+        let commit_s = points.vector_commit([&sl, &sr], &salt_beta);
+        transcript.append_point(b"A", &commit_a);
+        transcript.append_point(b"S", &commit_s);
+        let y = transcript.challenge_scalar(b"y");
+        let z = transcript.challenge_scalar(b"z");
+
+        let yn = powers_gen(y, count);
+        let n2: Vec<Scalar> = ranges.iter().flat_map(|&n| n2_gen(n)).collect();
+        let (d, zn) = segment_weights(ranges, &n2, z, count);
+        let l0 = vector_sub(&al, &vec![z; count]);
+        let r0 = vector_add(&hadamard_multiply(&yn, &vector_add(&ar, &vec![z; count])), &d);
+        let r1 = hadamard_multiply(&yn, &sr);
+        let t1: Scalar = hadamard_multiply(&l0, &r1).iter().sum::<Scalar>() + hadamard_multiply(&sl, &r0).iter().sum::<Scalar>();
+        let t2: Scalar = hadamard_multiply(&sl, &r1).iter().sum();
+        let commit_t1 = points.commit(&t1, &salt_tau1);
+        let commit_t2 = points.commit(&t2, &salt_tau2);
+        transcript.append_point(b"T1", &commit_t1);
+        transcript.append_point(b"T2", &commit_t2);
+        let u = transcript.challenge_scalar(b"u");
+
+        let l = vector_add(&l0, &vec_scalar_mul(&sl, &u));
+        let r = vector_add(&r0, &vec_scalar_mul(&r1, &u));
+        let tu: Scalar = hadamard_multiply(&l, &r).iter().sum();
+        let pi_t = (salt_tau1 * u) + (salt_tau2 * u * u) + zn.iter().zip(openings.iter()).map(|(zj, x)| zj * x.gamma()).sum::<Scalar>();
+        let pi_lr = salt_alpha + (salt_beta * u);
+        transcript.append_scalar(b"t_x", &tu);
+        transcript.append_scalar(b"t_x_blinding", &pi_t);
+        transcript.append_scalar(b"e_blinding", &pi_lr);
+        let q = points.mul_g_i(&transcript.challenge_scalar(b"w"));
+
+        let y_inv_h = points_hadamard_multiply(&powers_gen(invert(&y), count), &h_basis);
+        let ipp = InnerProductProof::create(transcript, [g_basis, y_inv_h], q, [l, r]);
+
+        Ok((Self {
+            ranges: ranges.to_vec(),
+            commit_a,
+            commit_s,
+            commit_t1,
+            commit_t2,
+            tu,
+            pi_lr,
+            pi_t,
+            ipp,
+        }, commits))
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commits: &[RistrettoPoint], ranges: &[usize]) -> Result<(), ProofError> {
+        if commits.len() != ranges.len() {
+            return Err(ProofError::InvalidAggregation);
+        }
+
+        self.verify_with_key(transcript, &VerifierKey::aggregated(ranges, points)?, commits)
+    }
+
+    // For verifying many proofs with the same widths against the same generators, see VerifierKey::aggregated.
+    pub fn verify_with_key(&self, transcript: &mut Transcript, key: &VerifierKey, commits: &[RistrettoPoint]) -> Result<(), ProofError> {
+        if commits.len() != key.parties() {
+            return Err(ProofError::InvalidAggregation);
+        }
+        if key.ranges() != self.ranges {
+            return Err(ProofError::VerificationError);
+        }
+        let count = key.g_basis().len();
+
+        append_statement(transcript, commits, key.ranges());
+        transcript.append_point(b"A", &self.commit_a);
+        transcript.append_point(b"S", &self.commit_s);
+        let y = transcript.challenge_scalar(b"y");
+        let z = transcript.challenge_scalar(b"z");
+        transcript.append_point(b"T1", &self.commit_t1);
+        transcript.append_point(b"T2", &self.commit_t2);
+        let u = transcript.challenge_scalar(b"u");
+        transcript.append_scalar(b"t_x", &self.tu);
+        transcript.append_scalar(b"t_x_blinding", &self.pi_t);
+        transcript.append_scalar(b"e_blinding", &self.pi_lr);
+        let w = transcript.challenge_scalar(b"w");
+        let q = key.mul_g_i(&w);
+
+        let yn = powers_gen(y, count);
+        let (d, zn) = segment_weights(key.ranges(), key.n2(), z, count);
+        let delta = key.delta(yn.iter().sum(), z);
+
+        let eqn3lhs = key.mul_g_i(&self.tu) + key.mul_b_i(&self.pi_t);
+        let eqn3rhs = commits.iter().zip(zn.iter()).map(|(v, zj)| v * zj).sum::<RistrettoPoint>() + key.mul_g_i(&delta)
+            + (self.commit_t1 * u) + (self.commit_t2 * u * u);
+        if eqn3lhs != eqn3rhs {
+            return Err(ProofError::VerificationError);
+        }
+
+        let y_inv_h = points_hadamard_multiply(&powers_gen(invert(&y), count), key.h_basis());
+        let commit_c = self.commit_a + (self.commit_s * u) + key.g_term(z)
+            + inner_product(&vector_add(&vec_scalar_mul(&yn, &z), &d), &y_inv_h) - key.mul_b_i(&self.pi_lr);
+
+        self.ipp.verify(transcript, commit_c + (self.tu * q), [key.g_basis().to_vec(), y_inv_h], q)
+    }
+
+    // Version, mode, the number of values and each bit width, then A, S, T1, T2, t, pi_t, pi_lr
+    // and the inner-product proof. The widths fix the number of folding rounds.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![PROTOCOL_VERSION, AGGREGATED_MODE, self.ranges.len() as u8];
+        bytes.extend(self.ranges.iter().map(|&n| n as u8));
+        write_points(&mut bytes, &[self.commit_a, self.commit_s, self.commit_t1, self.commit_t2]);
+        write_scalars(&mut bytes, &[self.tu, self.pi_t, self.pi_lr]);
+        bytes.extend(self.ipp.to_bytes());
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = Reader::new(bytes);
+        let ranges = read_widths(&mut reader)?;
+
+        AggregatedRangeProof::read_body(reader, ranges)
+    }
+
+    // Reads one proof off the front of `reader`, which may hold more after it.
+    pub(crate) fn read(reader: &mut Reader) -> Result<Self, ProofError> {
+        let ranges = read_widths(reader)?;
+        let rounds = padded_len(&ranges).trailing_zeros() as usize;
+
+        AggregatedRangeProof::read_body(reader.take((7 * 32) + ((rounds + 1) * 64))?, ranges)
+    }
+
+    fn read_body(mut reader: Reader, ranges: Vec<usize>) -> Result<Self, ProofError> {
+        let [commit_a, commit_s, commit_t1, commit_t2] = [reader.read_point()?, reader.read_point()?, reader.read_point()?, reader.read_point()?];
+        let [tu, pi_t, pi_lr] = [reader.read_scalar()?, reader.read_scalar()?, reader.read_scalar()?];
+        let ipp = InnerProductProof::read(&mut reader, padded_len(&ranges).trailing_zeros() as usize)?;
+        reader.finish()?;
+
+        Ok(Self {
+            ranges,
+            commit_a,
+            commit_s,
+            commit_t1,
+            commit_t2,
+            tu,
+            pi_lr,
+            pi_t,
+            ipp,
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?)
+    }
+
+    pub fn to_armored(&self) -> String {
+        armor_plain("AggregatedRangeProof", &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<Self, ProofError> {
+        Self::from_bytes(&dearmor_plain(text, "AggregatedRangeProof")?)
+    }
+
+    pub fn ranges(&self) -> &[usize] {
+        &self.ranges
+    }
+
+    pub fn commit_a(&self) -> RistrettoPoint {
+        self.commit_a
+    }
+
+    pub fn commit_s(&self) -> RistrettoPoint {
+        self.commit_s
+    }
+
+    pub fn commit_t1(&self) -> RistrettoPoint {
+        self.commit_t1
+    }
+
+    pub fn commit_t2(&self) -> RistrettoPoint {
+        self.commit_t2
+    }
+
+    pub fn tu(&self) -> Scalar {
+        self.tu
+    }
+
+    pub fn pi_lr(&self) -> Scalar {
+        self.pi_lr
+    }
+
+    pub fn pi_t(&self) -> Scalar {
+        self.pi_t
+    }
+
+    pub fn ipp(&self) -> &InnerProductProof {
+        &self.ipp
+    }
+}
+
+fn check_ranges(points: &GlobalPoints, values: usize, ranges: &[usize]) -> Result<usize, ProofError> {
+    if values != ranges.len() {
+        return Err(ProofError::InvalidAggregation);
+    }
+    let count = check_widths(ranges)?;
+    if points.g_basis().len() < count {
+        return Err(ProofError::InvalidGeneratorsLength);
+    }
+
+    Ok(count)
+}
+
+// One to 255 values, so the count fits the encoding's length byte, each of 1 to 64 bits.
+// Returns the padded bit length.
+pub(crate) fn check_widths(ranges: &[usize]) -> Result<usize, ProofError> {
+    if ranges.is_empty() || ranges.len() > u8::MAX as usize {
+        return Err(ProofError::InvalidAggregation);
+    }
+    if ranges.iter().any(|&n| n == 0 || n > 64) {
+        return Err(ProofError::InvalidBitsize);
+    }
+
+    Ok(padded_len(ranges))
+}
+
+fn padded_len(ranges: &[usize]) -> usize {
+    ranges.iter().sum::<usize>().next_power_of_two()
+}
+
+// The version, mode, value count and widths in front of an encoded proof.
+fn read_widths(reader: &mut Reader) -> Result<Vec<usize>, ProofError> {
+    match reader.read_u8()? {
+        PROTOCOL_VERSION => {}
+        version => return Err(DecodeError::UnknownVersion(version).into()),
+    }
+    match reader.read_u8()? {
+        AGGREGATED_MODE => {}
+        mode => return Err(DecodeError::UnknownMode(mode).into()),
+    }
+    let values = reader.read_u8()?;
+    let ranges = (0..values).map(|_| reader.read_u8().map(usize::from)).collect::<Result<Vec<usize>, DecodeError>>()?;
+    check_widths(&ranges)?;
+
+    Ok(ranges)
+}
+
+// The widths go into the encoding as single bytes, so they are checked as they are in from_bytes.
+#[cfg(feature = "serde")]
+fn deserialize_widths<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<usize>, D::Error> {
+    let ranges = Vec::<usize>::deserialize(deserializer)?;
+    check_widths(&ranges).map_err(serde::de::Error::custom)?;
+
+    Ok(ranges)
+}
+
+fn append_statement(transcript: &mut Transcript, commits: &[RistrettoPoint], ranges: &[usize]) {
+    transcript.append_message(b"dom-sep", b"aggregated rangeproof v1");
+    transcript.append_u64(b"m", commits.len() as u64);
+    for (commit, &range) in commits.iter().zip(ranges.iter()) {
+        transcript.append_u64(b"n", range as u64);
+        transcript.append_point(b"V", commit);
+    }
+}
+
+// d holds z^(2+j) * 2^i over the bits of value j and zero over the padding; zn holds the z^(2+j) themselves.
+// n2 holds the powers of two of every value back to back, as in VerifierKey::n2.
+fn segment_weights(ranges: &[usize], n2: &[Scalar], z: Scalar, count: usize) -> (Vec<Scalar>, Vec<Scalar>) {
+    let mut d = vec![];
+    let mut zn = vec![];
+    let mut zj = z * z;
+    let mut start = 0;
+    for &range in ranges {
+        d.extend(vec_scalar_mul(&n2[start..start + range], &zj));
+        zn.push(zj);
+        zj *= z;
+        start += range;
+    }
+    d.resize(count, Scalar::ZERO);
+
+    (d, zn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prove(points: &GlobalPoints, values: &[u64], ranges: &[usize]) -> Result<(AggregatedRangeProof, Vec<RistrettoPoint>), ProofError> {
+        let openings: Vec<Opening> = values.iter().map(|&v| Opening::random(v)).collect();
+        AggregatedRangeProof::prove(&mut Transcript::new(b"test"), points, &openings, ranges)
+    }
+
+    #[test]
+    fn prove_verify_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commits) = prove(&points, &[200, 3, 40000], &[8, 2, 16]).unwrap();
+
+        assert_eq!(proof.verify(&mut Transcript::new(b"test"), &points, &commits, &[8, 2, 16]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_swapped_commitments() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, mut commits) = prove(&points, &[200, 7], &[8, 8]).unwrap();
+        commits.swap(0, 1);
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, &commits, &[8, 8]).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_transcript_or_ranges() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, commits) = prove(&points, &[200, 7], &[8, 8]).unwrap();
+
+        assert!(proof.verify(&mut Transcript::new(b"other"), &points, &commits, &[8, 8]).is_err());
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, &commits, &[8, 4]).is_err());
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commits) = prove(&points, &[200, 3, 40000], &[8, 2, 16]).unwrap();
+        let bytes = proof.to_bytes();
+        let decoded = AggregatedRangeProof::from_bytes(&bytes).unwrap();
+
+        assert_eq!(&bytes[..5], &[PROTOCOL_VERSION, AGGREGATED_MODE, 3, 8, 2]);
+        assert_eq!(decoded.ranges(), &[8, 2, 16]);
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.verify(&mut Transcript::new(b"test"), &points, &commits, &[8, 2, 16]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, _) = prove(&points, &[200, 7], &[8, 8]).unwrap();
+        let bytes = proof.to_bytes();
+        let with = |i: usize, byte: u8| {
+            let mut bytes = bytes.clone();
+            bytes[i] = byte;
+            AggregatedRangeProof::from_bytes(&bytes).err()
+        };
+
+        assert_eq!(with(0, 2), Some(DecodeError::UnknownVersion(2).into()));
+        assert_eq!(with(1, 0), Some(DecodeError::UnknownMode(0).into()));
+        assert_eq!(with(2, 0), Some(ProofError::InvalidAggregation));
+        assert_eq!(with(3, 65), Some(ProofError::InvalidBitsize));
+        assert_eq!(with(3, 16), Some(DecodeError::RoundCountMismatch { expected: 5, found: 4 }.into()));
+        assert_eq!(AggregatedRangeProof::from_bytes(&[&bytes[..], &[0]].concat()).err(), Some(DecodeError::TrailingBytes.into()));
+    }
+
+    #[test]
+    fn rejects_bad_statements() {
+        let points = GlobalPoints::gen_global(16);
+
+        assert!(matches!(prove(&points, &[200, 7], &[8]), Err(ProofError::InvalidAggregation)));
+        assert!(matches!(prove(&points, &[256], &[8]), Err(ProofError::ValueOutOfRange)));
+        assert!(matches!(prove(&points, &[1, 1, 1], &[8, 8, 8]), Err(ProofError::InvalidGeneratorsLength)));
+    }
+
+    #[test]
+    fn hex_and_armor_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commits) = prove(&points, &[200, 3, 40000], &[8, 2, 16]).unwrap();
+        let from_hex = AggregatedRangeProof::from_hex(&proof.to_hex()).unwrap();
+        let from_armor = AggregatedRangeProof::from_armored(&proof.to_armored()).unwrap();
+
+        assert_eq!(from_hex.to_bytes(), proof.to_bytes());
+        assert_eq!(from_armor.to_bytes(), proof.to_bytes());
+        assert_eq!(from_armor.verify(&mut Transcript::new(b"test"), &points, &commits, &[8, 2, 16]), Ok(()));
+        assert_eq!(AggregatedRangeProof::from_hex(&proof.to_hex().to_uppercase()).err(), Some(DecodeError::InvalidHex.into()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commits) = prove(&points, &[200, 3, 40000], &[8, 2, 16]).unwrap();
+        let json = serde_json::to_value(&proof).unwrap();
+        let decoded: AggregatedRangeProof = serde_json::from_value(json.clone()).unwrap();
+        let mut wide = json.clone();
+        wide["ranges"][0] = 65.into();
+
+        assert_eq!(decoded.to_bytes(), proof.to_bytes());
+        assert_eq!(decoded.verify(&mut Transcript::new(b"test"), &points, &commits, &[8, 2, 16]), Ok(()));
+        assert!(serde_json::from_value::<AggregatedRangeProof>(wide).is_err());
+    }
+
+    #[test]
+    fn verify_with_key_reuses_one_key() {
+        let points = GlobalPoints::gen_global(32).with_tables();
+        let key = VerifierKey::aggregated(&[8, 2, 16], &points).unwrap();
+        for values in [[200, 3, 40000], [0, 0, 0], [255, 3, 65535]] {
+            let (proof, commits) = prove(&points, &values, &[8, 2, 16]).unwrap();
+
+            assert_eq!(proof.verify_with_key(&mut Transcript::new(b"test"), &key, &commits), Ok(()));
+        }
+    }
+
+    #[test]
+    fn verify_with_key_checks_the_shape() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commits) = prove(&points, &[200, 3, 40000], &[8, 2, 16]).unwrap();
+        let other = VerifierKey::aggregated(&[8, 16, 2], &points).unwrap();
+
+        assert_eq!(proof.verify_with_key(&mut Transcript::new(b"test"), &other, &commits), Err(ProofError::VerificationError));
+        assert_eq!(proof.verify_with_key(&mut Transcript::new(b"test"), &other, &commits[..2]), Err(ProofError::InvalidAggregation));
+        assert_eq!(proof.verify_with_key(&mut Transcript::new(b"test"), &VerifierKey::new(8, &points).unwrap(), &commits[..1]), Err(ProofError::VerificationError));
+    }
+}
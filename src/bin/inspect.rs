@@ -0,0 +1,65 @@
+use std::env;
+use std::io::{self, Read};
+use std::process;
+
+use range_proof::generator::GlobalPoints;
+use range_proof::inspect::inspect;
+use range_proof::text::from_hex;
+
+// Usage: inspect [--bits N] [--commitment HEX] [--generators dalek|FILE] [PROOF_HEX]
+// Reads the proof from stdin when it is not given as an argument. `--generators dalek` checks
+// against GlobalPoints::gen_dalek, a FILE holds GlobalPoints as JSON and needs the serde feature.
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut bits = None;
+    let mut commitment = None;
+    let mut generators = None;
+    let mut proof = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bits" => bits = Some(args.next().and_then(|x| x.parse().ok()).unwrap_or_else(|| fail("--bits needs a number"))),
+            "--commitment" => commitment = Some(args.next().unwrap_or_else(|| fail("--commitment needs a hex value"))),
+            "--generators" => generators = Some(args.next().unwrap_or_else(|| fail("--generators needs dalek or a file"))),
+            _ => proof = Some(arg),
+        }
+    }
+    let proof = proof.unwrap_or_else(|| {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input).unwrap_or_else(|err| fail(&err.to_string()));
+        input
+    });
+
+    let mut summary = inspect(&decode_hex(&proof), bits).unwrap_or_else(|err| fail(&err.to_string()));
+    if let Some(commitment) = commitment {
+        summary = summary.with_commitment(&decode_hex(&commitment)).unwrap_or_else(|err| fail(&err.to_string()));
+    }
+    if let Some(generators) = generators {
+        let points = load_generators(&generators, summary.generators_needed());
+        summary = summary.with_generators(&points).unwrap_or_else(|err| fail(&err.to_string()));
+    }
+    print!("{}", summary);
+}
+
+fn decode_hex(hex: &str) -> Vec<u8> {
+    let hex: String = hex.chars().filter(|x| !x.is_whitespace()).collect();
+
+    from_hex(&hex).unwrap_or_else(|err| fail(&err.to_string()))
+}
+
+fn load_generators(source: &str, capacity: usize) -> GlobalPoints {
+    if source == "dalek" {
+        return GlobalPoints::gen_dalek(capacity);
+    }
+    #[cfg(feature = "serde")]
+    {
+        let text = std::fs::read_to_string(source).unwrap_or_else(|err| fail(&format!("{}: {}", source, err)));
+        serde_json::from_str(&text).unwrap_or_else(|err| fail(&format!("{}: {}", source, err)))
+    }
+    #[cfg(not(feature = "serde"))]
+    fail("reading generators from a file needs the serde feature")
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("inspect: {}", message);
+    process::exit(1)
+}
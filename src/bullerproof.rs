@@ -1,53 +1,272 @@
 use curve25519_dalek::traits::Identity;
 use curve25519_dalek::{RistrettoPoint, Scalar};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+use crate::errors::ProofError;
 use crate::generator::GlobalPoints;
+use crate::logging::{event, Phase};
+use crate::metrics::{count_ops, Metrics, ProofPhase};
+use crate::operations::{invert, multiscalar_mul};
 use crate::prover::BulletProof;
+use crate::serialization::{write_points, write_scalars, Reader};
+use crate::transcript::Transcript;
 use crate::verifier::{Generatives, BulletVerify};
+#[cfg(feature = "serde")]
+use crate::text::{hex_points, hex_scalar};
 
-pub fn prove_commitments_log(count: usize, points: &GlobalPoints, prover: &mut BulletProof, verifier: &mut BulletVerify, gen: &Generatives) -> () {
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InnerProductProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
+    l_vec: Vec<RistrettoPoint>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
+    r_vec: Vec<RistrettoPoint>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    a: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    b: Scalar,
+}
+
+pub fn prove_commitments_log(count: usize, points: &GlobalPoints, prover: &mut BulletProof, verifier: &mut BulletVerify, gen: &Generatives) -> Result<(), ProofError> {
     if prover.a().len() <= 1 {
-        verifier.verify([prover.a(), prover.b()], count, points, gen);
+        verifier.verify([prover.a(), prover.b()], count, points, gen)
     } else {
-        let [left, right] = BulletProof::compute_diagonal([prover.a().clone(), prover.b().clone()], [&mut prover.g_basis(), &mut prover.h_basis()], points.g_i());
+        event!(length = prover.a().len(), "folding round");
+        let [left, right] = BulletProof::compute_diagonal([prover.a(), prover.b()], [prover.g_basis(), prover.h_basis()], points.g_i());
         verifier.u_gen();
         let u_random = verifier.u_random();
         verifier.compute([left, right]);
         prover.update_diagonals([left, right]);
         prover.compute(u_random);
-        prove_commitments_log(count, points, prover, verifier, gen);
+        prove_commitments_log(count, points, prover, verifier, gen)
     }
 }
 
-pub fn fold_scalar(a: &mut Vec<Scalar>, u: Scalar) -> Vec<Scalar> {
-    if a.len() % 2 !=0 {
+// Folds each pair into first * a[2i] + second * a[2i + 1], in the front half of the vector, and
+// truncates it, so no new buffer is allocated. An odd length gets a zero in front first. The
+// weights are a round challenge and its inverse, which the caller inverts once per round.
+pub fn fold_scalar(a: &mut Vec<Scalar>, first: Scalar, second: Scalar) {
+    if !a.len().is_multiple_of(2) {
         a.insert(0, Scalar::from(0u8));
     }
-    
-    let folded: Vec<Scalar> = a.chunks(2).map(|chunk| {
-        match chunk {
-            [x, y] => (x * u) + (y * u.invert()),
-            _ => panic!("Scalar folding error")
-        }
-    }).collect();
 
-    // assert_eq!(a.len(), folded.len() * 2, "Scalar folded incorrectly");
-    folded
+    for i in 0..a.len() / 2 {
+        a[i] = (a[2 * i] * first) + (a[2 * i + 1] * second);
+    }
+    a.truncate(a.len() / 2);
 }
 
-pub fn fold_vector(a: &mut Vec<RistrettoPoint>, u: Scalar) -> Vec<RistrettoPoint> {
-    if a.len() % 2 !=0 {
+pub fn fold_vector(a: &mut Vec<RistrettoPoint>, first: Scalar, second: Scalar) {
+    if !a.len().is_multiple_of(2) {
         a.insert(0, RistrettoPoint::identity());
     }
 
-    let folded: Vec<RistrettoPoint> = a.chunks(2).map(|chunk| {
-        match chunk {
-            [x, y] => (x * u) + (y * u.invert()),
-            _ => panic!("Scalar folding error")
+    count_ops(a.len(), a.len() / 2, 0);
+    for i in 0..a.len() / 2 {
+        a[i] = (a[2 * i] * first) + (a[2 * i + 1] * second);
+    }
+    a.truncate(a.len() / 2);
+}
+
+impl InnerProductProof {
+    // Proves knowledge of a, b with P = <a, G> + <b, H> + <a, b> * q, folding the same way
+    // as prove_commitments_log but with the round challenges taken from the transcript.
+    pub fn create(transcript: &mut Transcript, bases: [Vec<RistrettoPoint>; 2], q: RistrettoPoint, ab: [Vec<Scalar>; 2]) -> Self {
+        InnerProductProof::create_with_metrics(transcript, bases, q, ab, &mut Metrics::new())
+    }
+
+    // Same as create, with every folding round recorded as its own phase.
+    pub fn create_with_metrics(transcript: &mut Transcript, [mut g_basis, mut h_basis]: [Vec<RistrettoPoint>; 2], q: RistrettoPoint, [mut a, mut b]: [Vec<Scalar>; 2], metrics: &mut Metrics) -> Self {
+        let _phase = Phase::enter("inner product proof", a.len());
+        let mut l_vec = vec![];
+        let mut r_vec = vec![];
+
+        while a.len() > 1 {
+            event!(round = l_vec.len(), length = a.len(), "folding round");
+            metrics.measure(ProofPhase::Folding { round: l_vec.len() }, || {
+                let [left, right] = BulletProof::compute_diagonal([&a, &b], [&g_basis, &h_basis], q);
+                transcript.append_point(b"L", &left);
+                transcript.append_point(b"R", &right);
+                let u = transcript.challenge_scalar(b"u");
+                let u_inv = invert(&u);
+
+                fold_vector(&mut g_basis, u_inv, u);
+                fold_vector(&mut h_basis, u, u_inv);
+                fold_scalar(&mut a, u, u_inv);
+                fold_scalar(&mut b, u_inv, u);
+                l_vec.push(left);
+                r_vec.push(right);
+            });
+        }
+
+        Self {
+            l_vec,
+            r_vec,
+            a: a[0],
+            b: b[0],
         }
-    }).collect();
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, mut commit_p: RistrettoPoint, [mut g_basis, mut h_basis]: [Vec<RistrettoPoint>; 2], q: RistrettoPoint) -> Result<(), ProofError> {
+        if self.l_vec.len() != self.r_vec.len() || self.l_vec.len() >= 32 || g_basis.len() != h_basis.len() || g_basis.len() != 1 << self.l_vec.len() {
+            return Err(ProofError::VerificationError);
+        }
+
+        for (left, right) in self.l_vec.iter().zip(self.r_vec.iter()) {
+            transcript.append_point(b"L", left);
+            transcript.append_point(b"R", right);
+            let u = transcript.challenge_scalar(b"u");
+            let u_inv = invert(&u);
+
+            commit_p += multiscalar_mul(&[(&[u * u, u_inv * u_inv], &[*left, *right])]);
+            fold_vector(&mut g_basis, u_inv, u);
+            fold_vector(&mut h_basis, u, u_inv);
+        }
+
+        if commit_p == multiscalar_mul(&[(&[self.a, self.b, self.a * self.b], &[g_basis[0], h_basis[0], q])]) {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    // L_0, R_0, .., L_k, R_k, a, b
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (left, right) in self.l_vec.iter().zip(self.r_vec.iter()) {
+            write_points(&mut bytes, &[*left, *right]);
+        }
+        write_scalars(&mut bytes, &[self.a, self.b]);
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = Reader::new(bytes);
+        let rounds = reader.rounds(64)?;
+        let proof = InnerProductProof::read(&mut reader, rounds)?;
+        reader.finish()?;
+
+        Ok(proof)
+    }
+
+    pub(crate) fn read(reader: &mut Reader, rounds: usize) -> Result<Self, ProofError> {
+        reader.expect_rounds(64, rounds)?;
+        let mut l_vec = vec![];
+        let mut r_vec = vec![];
+        for _ in 0..rounds {
+            l_vec.push(reader.read_point()?);
+            r_vec.push(reader.read_point()?);
+        }
+
+        Ok(Self {
+            l_vec,
+            r_vec,
+            a: reader.read_scalar()?,
+            b: reader.read_scalar()?,
+        })
+    }
 
-    // assert_eq!(a.len(), folded.len() * 2, "Vector folded incorrectly");
-    folded
+    pub fn l_vec(&self) -> Vec<RistrettoPoint> {
+        self.l_vec.clone()
+    }
+
+    pub fn r_vec(&self) -> Vec<RistrettoPoint> {
+        self.r_vec.clone()
+    }
+
+    pub fn a(&self) -> Scalar {
+        self.a
+    }
+
+    pub fn b(&self) -> Scalar {
+        self.b
+    }
 }
+#[cfg(test)]
+mod tests {
+    use rand_core::OsRng;
 
+    use super::*;
+    use crate::operations::inner_product;
+
+    fn scalars(values: &[u64]) -> Vec<Scalar> {
+        values.iter().map(|x| Scalar::from(*x)).collect()
+    }
+
+    fn proof(n: usize, transcript: &mut Transcript) -> (InnerProductProof, RistrettoPoint, [Vec<RistrettoPoint>; 2], RistrettoPoint) {
+        let points = GlobalPoints::gen_global(n);
+        let a: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut OsRng)).collect();
+        let b: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut OsRng)).collect();
+        let q = RistrettoPoint::random(&mut OsRng);
+        let commit_p = inner_product(&a, points.g_basis()) + inner_product(&b, points.h_basis()) + (inner_product_scalars(&a, &b) * q);
+        let bases = [points.g_basis().to_vec(), points.h_basis().to_vec()];
+
+        (InnerProductProof::create(transcript, bases.clone(), q, [a, b]), commit_p, bases, q)
+    }
+
+    fn inner_product_scalars(a: &[Scalar], b: &[Scalar]) -> Scalar {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn folds_in_place() {
+        let mut a = scalars(&[1, 2, 3, 4]);
+        let buffer = a.as_ptr();
+        fold_scalar(&mut a, Scalar::from(2u8), Scalar::from(3u8));
+
+        assert_eq!(a, scalars(&[8, 18]));
+        assert_eq!(a.as_ptr(), buffer);
+    }
+
+    #[test]
+    fn pads_odd_lengths_in_front() {
+        let mut a = scalars(&[5, 6, 7]);
+        fold_scalar(&mut a, Scalar::from(2u8), Scalar::from(3u8));
+        let points: Vec<RistrettoPoint> = (0..3).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let mut folded = points.clone();
+        fold_vector(&mut folded, Scalar::from(2u8), Scalar::from(3u8));
+
+        assert_eq!(a, scalars(&[15, 33]));
+        assert_eq!(folded, vec![points[0] * Scalar::from(3u8), (points[1] * Scalar::from(2u8)) + (points[2] * Scalar::from(3u8))]);
+    }
+
+    #[test]
+    fn create_verify_round_trip() {
+        let (ipp, commit_p, bases, q) = proof(8, &mut Transcript::new(b"test"));
+
+        assert_eq!(ipp.l_vec().len(), 3);
+        assert_eq!(ipp.verify(&mut Transcript::new(b"test"), commit_p, bases, q), Ok(()));
+    }
+
+    #[test]
+    fn rejects_wrong_statement() {
+        let (ipp, commit_p, bases, q) = proof(8, &mut Transcript::new(b"test"));
+        let [g_basis, h_basis] = bases.clone();
+
+        assert_eq!(ipp.verify(&mut Transcript::new(b"other"), commit_p, bases.clone(), q), Err(ProofError::VerificationError));
+        assert_eq!(ipp.verify(&mut Transcript::new(b"test"), commit_p + q, bases, q), Err(ProofError::VerificationError));
+        assert_eq!(ipp.verify(&mut Transcript::new(b"test"), commit_p, [g_basis[..4].to_vec(), h_basis[..4].to_vec()], q), Err(ProofError::VerificationError));
+    }
+
+    #[test]
+    fn inverts_each_challenge_once_per_round() {
+        let points = GlobalPoints::gen_global(8);
+        let a: Vec<Scalar> = (0..8).map(|_| Scalar::random(&mut OsRng)).collect();
+        let mut metrics = Metrics::new();
+        InnerProductProof::create_with_metrics(&mut Transcript::new(b"test"), [points.g_basis().to_vec(), points.h_basis().to_vec()], points.g_i(), [a.clone(), a], &mut metrics);
+
+        assert_eq!(metrics.phases().iter().map(|x| x.counts().inversions()).collect::<Vec<u64>>(), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let (ipp, commit_p, bases, q) = proof(8, &mut Transcript::new(b"test"));
+        let bytes = ipp.to_bytes();
+        let decoded = InnerProductProof::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.verify(&mut Transcript::new(b"test"), commit_p, bases, q), Ok(()));
+        assert!(InnerProductProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,412 @@
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use rand_core::OsRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ProofError;
+use crate::generator::{n2_gen, powers_gen, FixedBase, GlobalPoints};
+use crate::metrics::{count_ops, Metrics, ProofPhase};
+use crate::operations::{invert, multiscalar_mul, scalarize, vector_sub};
+use crate::prover::{ASVcommitment, Opening};
+use crate::rangeproof::{check_bitsize, check_range};
+use crate::serialization::{write_points, write_scalars, Reader};
+use crate::text::{armor, dearmor, from_hex, to_hex};
+use crate::transcript::Transcript;
+#[cfg(feature = "serde")]
+use crate::text::{hex_point, hex_points, hex_scalar};
+
+// Zero-knowledge weighted inner-product argument for P = <a, G> + <b, H> + (a (.)_y b) * g + alpha * h,
+// where a (.)_y b = sum of a_i * b_i * y^i for i = 1..n.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WeightedInnerProductProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
+    l_vec: Vec<RistrettoPoint>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
+    r_vec: Vec<RistrettoPoint>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_a: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_b: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_r: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_s: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_d: Scalar,
+}
+
+// Bulletproofs+ range proof: a single commitment A, then the weighted inner-product argument
+// replaces S, T1, T2, tau_x, mu and t of the original protocol.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BulletproofsPlusProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_a: RistrettoPoint,
+    wip: WeightedInnerProductProof,
+}
+
+impl WeightedInnerProductProof {
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(transcript: &mut Transcript, bases: [Vec<RistrettoPoint>; 2], gh: [RistrettoPoint; 2], y: Scalar, ab: [Vec<Scalar>; 2], alpha: Scalar) -> Self {
+        WeightedInnerProductProof::create_with_metrics(transcript, bases, gh, y, ab, alpha, &mut Metrics::new())
+    }
+
+    // Same as create, with every folding round recorded as its own phase.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_with_metrics(transcript: &mut Transcript, bases: [Vec<RistrettoPoint>; 2], [g, h]: [RistrettoPoint; 2], y: Scalar, ab: [Vec<Scalar>; 2], alpha: Scalar, metrics: &mut Metrics) -> Self {
+        WeightedInnerProductProof::create_with_tables(transcript, bases, [&FixedBase::plain(g), &FixedBase::plain(h)], y, ab, alpha, metrics)
+    }
+
+    // g and h as FixedBase, so the prover's g_i and b_i multiplications use their tables when it has them.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn create_with_tables(transcript: &mut Transcript, [mut g_basis, mut h_basis]: [Vec<RistrettoPoint>; 2], gh: [&FixedBase; 2], y: Scalar, [mut a, mut b]: [Vec<Scalar>; 2], mut alpha: Scalar, metrics: &mut Metrics) -> Self {
+        let mut rng = OsRng;
+        let yn = powers_gen(y, a.len() + 1);
+        let mut l_vec = vec![];
+        let mut r_vec = vec![];
+
+        while a.len() > 1 {
+            metrics.measure(ProofPhase::Folding { round: l_vec.len() }, || {
+                let half = a.len() / 2;
+                let (a1, a2) = a.split_at(half);
+                let (b1, b2) = b.split_at(half);
+                let (g1, g2) = g_basis.split_at(half);
+                let (h1, h2) = h_basis.split_at(half);
+                let [y_half, y_half_inv] = [yn[half], invert(&yn[half])];
+
+                let c_l = weighted_product(a1, b2, &yn);
+                let c_r = y_half * weighted_product(a2, b1, &yn);
+                let [d_l, d_r] = [Scalar::random(&mut rng), Scalar::random(&mut rng)];
+                let a1_scaled: Vec<Scalar> = a1.iter().map(|x| x * y_half_inv).collect();
+                let a2_scaled: Vec<Scalar> = a2.iter().map(|x| x * y_half).collect();
+                let left = gh_mul(&[(&a1_scaled, g2), (b2, h1)], gh, [c_l, d_l]);
+                let right = gh_mul(&[(&a2_scaled, g1), (b1, h2)], gh, [c_r, d_r]);
+                transcript.append_point(b"L", &left);
+                transcript.append_point(b"R", &right);
+                let e = transcript.challenge_scalar(b"e");
+                let e_inv = invert(&e);
+
+                a = a1.iter().zip(a2).map(|(x1, x2)| (x1 * e) + (x2 * y_half * e_inv)).collect();
+                b = b1.iter().zip(b2).map(|(x1, x2)| (x1 * e_inv) + (x2 * e)).collect();
+                g_basis = fold_halves(&g_basis, e_inv, e * y_half_inv);
+                h_basis = fold_halves(&h_basis, e, e_inv);
+                alpha += (d_l * e * e) + (d_r * e_inv * e_inv);
+                l_vec.push(left);
+                r_vec.push(right);
+            });
+        }
+
+        let [r, s, delta, eta] = [(); 4].map(|_| Scalar::random(&mut rng));
+        let commit_a = gh_mul(&[(&[r, s], &[g_basis[0], h_basis[0]])], gh, [(r * y * b[0]) + (s * y * a[0]), delta]);
+        let commit_b = gh_mul(&[], gh, [r * y * s, eta]);
+        transcript.append_point(b"A", &commit_a);
+        transcript.append_point(b"B", &commit_b);
+        let e = transcript.challenge_scalar(b"e");
+
+        Self {
+            l_vec,
+            r_vec,
+            commit_a,
+            commit_b,
+            pi_r: r + (a[0] * e),
+            pi_s: s + (b[0] * e),
+            pi_d: eta + (delta * e) + (alpha * e * e),
+        }
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, [mut g_basis, mut h_basis]: [Vec<RistrettoPoint>; 2], [g, h]: [RistrettoPoint; 2], y: Scalar, mut commit_p: RistrettoPoint) -> Result<(), ProofError> {
+        if self.l_vec.len() != self.r_vec.len() || self.l_vec.len() >= 32 || g_basis.len() != h_basis.len() || g_basis.len() != 1 << self.l_vec.len() {
+            return Err(ProofError::VerificationError);
+        }
+        let yn = powers_gen(y, g_basis.len() + 1);
+
+        for (left, right) in self.l_vec.iter().zip(self.r_vec.iter()) {
+            transcript.append_point(b"L", left);
+            transcript.append_point(b"R", right);
+            let e = transcript.challenge_scalar(b"e");
+            let e_inv = invert(&e);
+            let half = g_basis.len() / 2;
+
+            commit_p += multiscalar_mul(&[(&[e * e, e_inv * e_inv], &[*left, *right])]);
+            g_basis = fold_halves(&g_basis, e_inv, e * invert(&yn[half]));
+            h_basis = fold_halves(&h_basis, e, e_inv);
+        }
+
+        transcript.append_point(b"A", &self.commit_a);
+        transcript.append_point(b"B", &self.commit_b);
+        let e = transcript.challenge_scalar(b"e");
+
+        let lhs = multiscalar_mul(&[(&[e * e, e, Scalar::ONE], &[commit_p, self.commit_a, self.commit_b])]);
+        let rhs = multiscalar_mul(&[(&[self.pi_r * e, self.pi_s * e, self.pi_r * y * self.pi_s, self.pi_d], &[g_basis[0], h_basis[0], g, h])]);
+        if lhs == rhs {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    // L_0, R_0, .., L_k, R_k, A, B, r', s', d'
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (left, right) in self.l_vec.iter().zip(self.r_vec.iter()) {
+            write_points(&mut bytes, &[*left, *right]);
+        }
+        write_points(&mut bytes, &[self.commit_a, self.commit_b]);
+        write_scalars(&mut bytes, &[self.pi_r, self.pi_s, self.pi_d]);
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = Reader::new(bytes);
+        let rounds = reader.rounds(160)?;
+        let proof = WeightedInnerProductProof::read(&mut reader, rounds)?;
+        reader.finish()?;
+
+        Ok(proof)
+    }
+
+    pub(crate) fn read(reader: &mut Reader, rounds: usize) -> Result<Self, ProofError> {
+        reader.expect_rounds(160, rounds)?;
+        let mut l_vec = vec![];
+        let mut r_vec = vec![];
+        for _ in 0..rounds {
+            l_vec.push(reader.read_point()?);
+            r_vec.push(reader.read_point()?);
+        }
+
+        Ok(Self {
+            l_vec,
+            r_vec,
+            commit_a: reader.read_point()?,
+            commit_b: reader.read_point()?,
+            pi_r: reader.read_scalar()?,
+            pi_s: reader.read_scalar()?,
+            pi_d: reader.read_scalar()?,
+        })
+    }
+
+    pub fn l_vec(&self) -> Vec<RistrettoPoint> {
+        self.l_vec.clone()
+    }
+
+    pub fn r_vec(&self) -> Vec<RistrettoPoint> {
+        self.r_vec.clone()
+    }
+
+    pub fn commit_a(&self) -> RistrettoPoint {
+        self.commit_a
+    }
+
+    pub fn commit_b(&self) -> RistrettoPoint {
+        self.commit_b
+    }
+
+    pub fn pi_r(&self) -> Scalar {
+        self.pi_r
+    }
+
+    pub fn pi_s(&self) -> Scalar {
+        self.pi_s
+    }
+
+    pub fn pi_d(&self) -> Scalar {
+        self.pi_d
+    }
+}
+
+impl BulletproofsPlusProof {
+    pub fn prove(transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, range: usize) -> Result<(Self, RistrettoPoint), ProofError> {
+        BulletproofsPlusProof::prove_with_metrics(transcript, points, opening, range, &mut Metrics::new())
+    }
+
+    // Same as prove, recording time and operation counts of the commitment and every folding round in `metrics`.
+    pub fn prove_with_metrics(transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, range: usize, metrics: &mut Metrics) -> Result<(Self, RistrettoPoint), ProofError> {
+        check_range(points, range)?;
+        if range < 64 && opening.value() >> range != 0 {
+            return Err(ProofError::ValueOutOfRange);
+        }
+        let g_basis = points.g_basis()[..range].to_vec();
+        let h_basis = points.h_basis()[..range].to_vec();
+
+        let [mut al, _] = ASVcommitment::compute_al_ar(opening.value(), range);
+        let al = scalarize(&mut al);
+        let ar = vector_sub(&al, &vec![Scalar::ONE; range]);
+        let salt_alpha = Scalar::random(&mut OsRng);
+        let (commit_v, commit_a) = metrics.measure(ProofPhase::Commitment, || {
+            (opening.commit(points), points.vector_commit([&al, &ar], &salt_alpha))
+        });
+        let (y, z) = append_statement(transcript, commit_v, commit_a, range);
+
+        let yn = powers_gen(y, range + 2);
+        let h_weights = h_weights(&yn, z, range);
+        let al_hat: Vec<Scalar> = al.iter().map(|x| x - z).collect();
+        let ar_hat: Vec<Scalar> = ar.iter().zip(h_weights.iter()).map(|(x, w)| x + w).collect();
+        let alpha_hat = salt_alpha + (opening.gamma() * yn[range + 1]);
+
+        let wip = WeightedInnerProductProof::create_with_tables(transcript, [g_basis, h_basis], [&points.fixed_g_i(), &points.fixed_b_i()], y, [al_hat, ar_hat], alpha_hat, metrics);
+
+        Ok((Self { commit_a, wip }, commit_v))
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, range: usize) -> Result<(), ProofError> {
+        self.verify_with_metrics(transcript, points, commit_v, range, &mut Metrics::new())
+    }
+
+    pub fn verify_with_metrics(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, range: usize, metrics: &mut Metrics) -> Result<(), ProofError> {
+        check_range(points, range)?;
+        metrics.measure(ProofPhase::Verification, || self.verify_equations(transcript, points, commit_v, range))
+    }
+
+    fn verify_equations(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, range: usize) -> Result<(), ProofError> {
+        let g_basis = points.g_basis()[..range].to_vec();
+        let h_basis = points.h_basis()[..range].to_vec();
+        let (y, z) = append_statement(transcript, commit_v, self.commit_a, range);
+
+        let yn = powers_gen(y, range + 2);
+        let zeta = ((z - (z * z)) * yn[1..=range].iter().sum::<Scalar>()) - (z * yn[range + 1] * n2_gen(range).iter().sum::<Scalar>());
+        let commit_a_hat = self.commit_a + multiscalar_mul(&[(&vec![-z; range], &g_basis), (&h_weights(&yn, z, range), &h_basis), (&[yn[range + 1]], &[commit_v])])
+            + points.mul_g_i(&zeta);
+
+        self.wip.verify(transcript, [g_basis, h_basis], [points.g_i(), points.b_i()], y, commit_a_hat)
+    }
+
+    // A followed by the weighted inner-product proof
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_points(&mut bytes, &[self.commit_a]);
+        bytes.extend(self.wip.to_bytes());
+
+        bytes
+    }
+
+    // The bit width fixes the number of folding rounds, so it is needed to decode.
+    pub fn from_bytes(bytes: &[u8], range: usize) -> Result<Self, ProofError> {
+        check_bitsize(range)?;
+        let mut reader = Reader::new(bytes);
+        let commit_a = reader.read_point()?;
+        let wip = WeightedInnerProductProof::read(&mut reader, range.trailing_zeros() as usize)?;
+        reader.finish()?;
+
+        Ok(Self { commit_a, wip })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str, range: usize) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?, range)
+    }
+
+    pub fn to_armored(&self, range: usize) -> String {
+        armor("BulletproofsPlusProof", range, &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<(Self, usize), ProofError> {
+        let (range, bytes) = dearmor(text, "BulletproofsPlusProof")?;
+
+        Ok((Self::from_bytes(&bytes, range)?, range))
+    }
+
+    pub fn commit_a(&self) -> RistrettoPoint {
+        self.commit_a
+    }
+
+    pub fn wip(&self) -> &WeightedInnerProductProof {
+        &self.wip
+    }
+}
+
+fn append_statement(transcript: &mut Transcript, commit_v: RistrettoPoint, commit_a: RistrettoPoint, range: usize) -> (Scalar, Scalar) {
+    transcript.append_message(b"dom-sep", b"bulletproofs plus v1");
+    transcript.append_u64(b"n", range as u64);
+    transcript.append_point(b"V", &commit_v);
+    transcript.append_point(b"A", &commit_a);
+
+    (transcript.challenge_scalar(b"y"), transcript.challenge_scalar(b"z"))
+}
+
+// z + 2^(i-1) * y^(n-i+1) for i = 1..n, the shift applied to a_R
+fn h_weights(yn: &[Scalar], z: Scalar, range: usize) -> Vec<Scalar> {
+    n2_gen(range).iter().enumerate().map(|(i, x)| z + (x * yn[range - i])).collect()
+}
+
+// <terms> + x * g + y * h. With tables g and h are multiplied through them, otherwise they join
+// the one multiscalar multiplication.
+fn gh_mul(terms: &[(&[Scalar], &[RistrettoPoint])], [g, h]: [&FixedBase; 2], [x, y]: [Scalar; 2]) -> RistrettoPoint {
+    if g.has_table() || h.has_table() {
+        multiscalar_mul(terms) + g.mul(&x) + h.mul(&y)
+    } else {
+        let (gh_scalars, gh_points) = ([x, y], [g.point(), h.point()]);
+        multiscalar_mul(&[terms, &[(&gh_scalars[..], &gh_points[..])]].concat())
+    }
+}
+
+// Weighted product over the first half: sum of a_i * b_i * y^i for i = 1..len
+fn weighted_product(a: &[Scalar], b: &[Scalar], yn: &[Scalar]) -> Scalar {
+    a.iter().zip(b).zip(yn[1..].iter()).map(|((x1, x2), y)| x1 * x2 * y).sum()
+}
+
+fn fold_halves(basis: &[RistrettoPoint], first: Scalar, second: Scalar) -> Vec<RistrettoPoint> {
+    let (basis1, basis2) = basis.split_at(basis.len() / 2);
+    count_ops(basis.len(), basis.len() / 2, 0);
+    basis1.iter().zip(basis2).map(|(x1, x2)| (x1 * first) + (x2 * second)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prove(value: u64, range: usize) -> (GlobalPoints, BulletproofsPlusProof, RistrettoPoint) {
+        let points = GlobalPoints::gen_global(range);
+        let (proof, commit_v) = BulletproofsPlusProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(value), range).unwrap();
+
+        (points, proof, commit_v)
+    }
+
+    #[test]
+    fn prove_verify_round_trip() {
+        for (value, range) in [(0, 8), (255, 8), (40000, 16)] {
+            let (points, proof, commit_v) = prove(value, range);
+
+            assert_eq!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, range), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_commitment_or_transcript() {
+        let (points, proof, commit_v) = prove(200, 8);
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v + points.g_i(), 8).is_err());
+        assert!(proof.verify(&mut Transcript::new(b"other"), &points, commit_v, 8).is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_proof() {
+        let (points, mut proof, commit_v) = prove(200, 8);
+        proof.wip.pi_r += Scalar::ONE;
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, 8).is_err());
+    }
+
+    #[test]
+    fn rejects_value_outside_range() {
+        let points = GlobalPoints::gen_global(8);
+        let result = BulletproofsPlusProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(256), 8);
+
+        assert!(matches!(result, Err(ProofError::ValueOutOfRange)));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let (points, proof, commit_v) = prove(200, 8);
+        let bytes = proof.to_bytes();
+        let decoded = BulletproofsPlusProof::from_bytes(&bytes, 8).unwrap();
+
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.verify(&mut Transcript::new(b"test"), &points, commit_v, 8), Ok(()));
+        assert!(BulletproofsPlusProof::from_bytes(&bytes, 16).is_err());
+        assert!(BulletproofsPlusProof::from_bytes(&bytes[..bytes.len() - 1], 8).is_err());
+    }
+}
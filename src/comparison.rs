@@ -0,0 +1,88 @@
+use curve25519_dalek::ristretto::RistrettoPoint;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ProofError;
+use crate::generator::GlobalPoints;
+use crate::prover::Opening;
+use crate::rangeproof::RangeProof;
+use crate::transcript::Transcript;
+
+// Proves v1 < v2 by range proving V2 - V1 - g_i, which commits to v2 - v1 - 1 with blinding gamma2 - gamma1.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ComparisonProof {
+    proof: RangeProof,
+}
+
+impl ComparisonProof {
+    pub fn prove(transcript: &mut Transcript, points: &GlobalPoints, [opening1, opening2]: [&Opening; 2], range: usize) -> Result<(Self, [RistrettoPoint; 2]), ProofError> {
+        if opening1.value() >= opening2.value() {
+            return Err(ProofError::ValueOutOfRange);
+        }
+        let commits = [opening1.commit(points), opening2.commit(points)];
+        append_statement(transcript, commits);
+
+        let difference = Opening::new(opening2.value() - opening1.value() - 1, opening2.gamma() - opening1.gamma());
+        let proof = RangeProof::prove_opening(transcript, points, &difference, difference_commit(points, commits), range)?;
+
+        Ok((Self { proof }, commits))
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commits: [RistrettoPoint; 2], range: usize) -> Result<(), ProofError> {
+        append_statement(transcript, commits);
+
+        self.proof.verify(transcript, points, difference_commit(points, commits), range)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.proof.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8], range: usize) -> Result<Self, ProofError> {
+        Ok(Self { proof: RangeProof::from_bytes(bytes, range)? })
+    }
+
+    pub fn proof(&self) -> &RangeProof {
+        &self.proof
+    }
+}
+
+fn difference_commit(points: &GlobalPoints, [commit1, commit2]: [RistrettoPoint; 2]) -> RistrettoPoint {
+    commit2 - commit1 - points.g_i()
+}
+
+fn append_statement(transcript: &mut Transcript, [commit1, commit2]: [RistrettoPoint; 2]) {
+    transcript.append_message(b"dom-sep", b"comparison proof v1");
+    transcript.append_point(b"V1", &commit1);
+    transcript.append_point(b"V2", &commit2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prove_verify_round_trip() {
+        let points = GlobalPoints::gen_global(8);
+        let (proof, commits) = ComparisonProof::prove(&mut Transcript::new(b"test"), &points, [&Opening::random(10), &Opening::random(200)], 8).unwrap();
+
+        assert_eq!(proof.verify(&mut Transcript::new(b"test"), &points, commits, 8), Ok(()));
+    }
+
+    #[test]
+    fn rejects_swapped_commitments() {
+        let points = GlobalPoints::gen_global(8);
+        let (proof, [commit1, commit2]) = ComparisonProof::prove(&mut Transcript::new(b"test"), &points, [&Opening::random(10), &Opening::random(200)], 8).unwrap();
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, [commit2, commit1], 8).is_err());
+        assert!(proof.verify(&mut Transcript::new(b"other"), &points, [commit1, commit2], 8).is_err());
+    }
+
+    #[test]
+    fn rejects_values_out_of_order() {
+        let points = GlobalPoints::gen_global(8);
+        let result = ComparisonProof::prove(&mut Transcript::new(b"test"), &points, [&Opening::random(10), &Opening::random(10)], 8);
+
+        assert!(matches!(result, Err(ProofError::ValueOutOfRange)));
+    }
+}
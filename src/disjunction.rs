@@ -0,0 +1,251 @@
+use std::ops::Range;
+
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use rand_core::OsRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DecodeError, ProofError};
+use crate::generator::GlobalPoints;
+use crate::interval::IntervalProof;
+use crate::prover::Opening;
+use crate::serialization::{write_points, write_scalars, write_u64, Reader};
+use crate::text::{armor_plain, dearmor_plain, from_hex, to_hex};
+use crate::transcript::Transcript;
+#[cfg(feature = "serde")]
+use crate::text::{hex_points, hex_scalars};
+
+// Proves V lies in one of several public intervals without revealing which. Every interval i
+// gets a fresh commitment W_i with an interval proof; the real branch re-commits v, the others
+// commit the interval's lower bound. An OR-Schnorr proof then shows W_i - V = r * b_i for some i.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DisjunctiveProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
+    branches: Vec<RistrettoPoint>,
+    interval_proofs: Vec<IntervalProof>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalars"))]
+    challenges: Vec<Scalar>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalars"))]
+    responses: Vec<Scalar>,
+}
+
+impl DisjunctiveProof {
+    pub fn prove(transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, intervals: &[Range<u64>]) -> Result<(Self, RistrettoPoint), ProofError> {
+        let real = intervals.iter().position(|x| x.contains(&opening.value())).ok_or(ProofError::ValueOutOfRange)?;
+        let commit_v = opening.commit(points);
+        append_intervals(transcript, commit_v, intervals);
+
+        let mut rng = OsRng;
+        let branch_openings: Vec<Opening> = intervals.iter().enumerate().map(|(i, x)| {
+            let value = if i == real { opening.value() } else { x.start };
+            Opening::new(value, Scalar::random(&mut rng))
+        }).collect();
+        let branches: Vec<RistrettoPoint> = branch_openings.iter().map(|x| x.commit(points)).collect();
+        for branch in branches.iter() {
+            transcript.append_point(b"W", branch);
+        }
+
+        let mut interval_proofs = vec![];
+        for (branch, interval) in branch_openings.iter().zip(intervals.iter()) {
+            let (proof, _) = IntervalProof::prove(transcript, points, branch, interval.clone())?;
+            interval_proofs.push(proof);
+        }
+
+        let mut challenges: Vec<Scalar> = (0..intervals.len()).map(|_| Scalar::random(&mut rng)).collect();
+        let mut responses: Vec<Scalar> = (0..intervals.len()).map(|_| Scalar::random(&mut rng)).collect();
+        let k = Scalar::random(&mut rng);
+        for (i, branch) in branches.iter().enumerate() {
+            let commit_r = if i == real {
+                k * points.b_i()
+            } else {
+                (responses[i] * points.b_i()) - (challenges[i] * (branch - commit_v))
+            };
+            transcript.append_point(b"R", &commit_r);
+        }
+
+        let c = transcript.challenge_scalar(b"c");
+        challenges[real] = c - challenges.iter().enumerate().filter(|(i, _)| *i != real).map(|(_, x)| x).sum::<Scalar>();
+        responses[real] = k + (challenges[real] * (branch_openings[real].gamma() - opening.gamma()));
+
+        Ok((Self {
+            branches,
+            interval_proofs,
+            challenges,
+            responses,
+        }, commit_v))
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, intervals: &[Range<u64>]) -> Result<(), ProofError> {
+        let count = intervals.len();
+        if count == 0 || [self.branches.len(), self.interval_proofs.len(), self.challenges.len(), self.responses.len()].iter().any(|&x| x != count) {
+            return Err(ProofError::VerificationError);
+        }
+        append_intervals(transcript, commit_v, intervals);
+        for branch in self.branches.iter() {
+            transcript.append_point(b"W", branch);
+        }
+
+        for ((proof, branch), interval) in self.interval_proofs.iter().zip(self.branches.iter()).zip(intervals.iter()) {
+            proof.verify(transcript, points, *branch, interval.clone())?;
+        }
+
+        for ((branch, c), s) in self.branches.iter().zip(self.challenges.iter()).zip(self.responses.iter()) {
+            let commit_r = (s * points.b_i()) - (c * (branch - commit_v));
+            transcript.append_point(b"R", &commit_r);
+        }
+
+        if transcript.challenge_scalar(b"c") == self.challenges.iter().sum::<Scalar>() {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    // The branch count, every W_i, challenge and response, then the interval proofs, each of
+    // which carries its own width.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_u64(&mut bytes, self.branches.len() as u64);
+        write_points(&mut bytes, &self.branches);
+        write_scalars(&mut bytes, &self.challenges);
+        write_scalars(&mut bytes, &self.responses);
+        for proof in self.interval_proofs.iter() {
+            bytes.extend(proof.to_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = Reader::new(bytes);
+        let count = reader.read_u64()?;
+        if count == 0 {
+            return Err(DecodeError::InvalidLength.into());
+        }
+        let branches = (0..count).map(|_| reader.read_point()).collect::<Result<Vec<RistrettoPoint>, DecodeError>>()?;
+        let challenges = (0..count).map(|_| reader.read_scalar()).collect::<Result<Vec<Scalar>, DecodeError>>()?;
+        let responses = (0..count).map(|_| reader.read_scalar()).collect::<Result<Vec<Scalar>, DecodeError>>()?;
+        let interval_proofs = (0..count).map(|_| IntervalProof::read(&mut reader)).collect::<Result<Vec<IntervalProof>, ProofError>>()?;
+        reader.finish()?;
+
+        Ok(Self {
+            branches,
+            interval_proofs,
+            challenges,
+            responses,
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?)
+    }
+
+    pub fn to_armored(&self) -> String {
+        armor_plain("DisjunctiveProof", &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<Self, ProofError> {
+        Self::from_bytes(&dearmor_plain(text, "DisjunctiveProof")?)
+    }
+
+    pub fn branches(&self) -> Vec<RistrettoPoint> {
+        self.branches.clone()
+    }
+
+    pub fn interval_proofs(&self) -> &[IntervalProof] {
+        &self.interval_proofs
+    }
+
+    pub fn challenges(&self) -> Vec<Scalar> {
+        self.challenges.clone()
+    }
+
+    pub fn responses(&self) -> Vec<Scalar> {
+        self.responses.clone()
+    }
+}
+
+fn append_intervals(transcript: &mut Transcript, commit_v: RistrettoPoint, intervals: &[Range<u64>]) {
+    transcript.append_message(b"dom-sep", b"disjunctive proof v1");
+    transcript.append_u64(b"k", intervals.len() as u64);
+    for interval in intervals {
+        transcript.append_u64(b"min", interval.start);
+        transcript.append_u64(b"max", interval.end);
+    }
+    transcript.append_point(b"V", &commit_v);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVALS: [Range<u64>; 3] = [0..10, 100..120, 200..230];
+
+    #[test]
+    fn prove_verify_round_trip() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, commit_v) = DisjunctiveProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(110), &INTERVALS).unwrap();
+
+        assert_eq!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, &INTERVALS), Ok(()));
+    }
+
+    #[test]
+    fn rejects_wrong_commitment_or_intervals() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, commit_v) = DisjunctiveProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(110), &INTERVALS).unwrap();
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v + points.g_i(), &INTERVALS).is_err());
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, &INTERVALS[..2]).is_err());
+        assert!(proof.verify(&mut Transcript::new(b"other"), &points, commit_v, &INTERVALS).is_err());
+    }
+
+    #[test]
+    fn rejects_value_in_no_interval() {
+        let points = GlobalPoints::gen_global(16);
+
+        assert!(matches!(DisjunctiveProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(50), &INTERVALS), Err(ProofError::ValueOutOfRange)));
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, commit_v) = DisjunctiveProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(110), &INTERVALS).unwrap();
+        let from_bytes = DisjunctiveProof::from_bytes(&proof.to_bytes()).unwrap();
+        let from_hex = DisjunctiveProof::from_hex(&proof.to_hex()).unwrap();
+        let from_armor = DisjunctiveProof::from_armored(&proof.to_armored()).unwrap();
+
+        assert_eq!(from_bytes.to_bytes(), proof.to_bytes());
+        assert_eq!(from_hex.to_bytes(), proof.to_bytes());
+        assert_eq!(from_armor.verify(&mut Transcript::new(b"test"), &points, commit_v, &INTERVALS), Ok(()));
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, _) = DisjunctiveProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(110), &INTERVALS).unwrap();
+        let bytes = proof.to_bytes();
+        let mut more = bytes.clone();
+        more[0] = 4;
+
+        assert_eq!(DisjunctiveProof::from_bytes(&[0u8; 8]).err(), Some(DecodeError::InvalidLength.into()));
+        assert_eq!(DisjunctiveProof::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(DecodeError::UnexpectedEnd.into()));
+        assert_eq!(DisjunctiveProof::from_bytes(&[&bytes[..], &[0]].concat()).err(), Some(DecodeError::TrailingBytes.into()));
+        assert!(DisjunctiveProof::from_bytes(&more).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, commit_v) = DisjunctiveProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(110), &INTERVALS).unwrap();
+        let decoded: DisjunctiveProof = serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+
+        assert_eq!(decoded.to_bytes(), proof.to_bytes());
+        assert_eq!(decoded.verify(&mut Transcript::new(b"test"), &points, commit_v, &INTERVALS), Ok(()));
+    }
+}
@@ -0,0 +1,249 @@
+use std::ops::Range;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+
+use crate::errors::ProofError;
+use crate::generator::GlobalPoints;
+use crate::interval::IntervalProof;
+use crate::prover::Opening;
+use crate::transcript::Transcript;
+
+// Order-preserving map from a domain type onto the u64 values the range proofs work with.
+// Params carries whatever decode needs besides the raw value, e.g. the scale of a decimal.
+pub trait ValueEncoder: Sized {
+    type Params: Copy + PartialEq;
+
+    fn params(&self) -> Self::Params;
+    fn encode(&self) -> u64;
+    fn decode(encoded: u64, params: Self::Params) -> Self;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Decimal {
+    mantissa: i64,
+    scale: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    secs: i64,
+}
+
+pub fn prove<T: ValueEncoder>(transcript: &mut Transcript, points: &GlobalPoints, value: T, interval: Range<T>) -> Result<(IntervalProof, RistrettoPoint, Opening), ProofError> {
+    let interval = encode_interval(&interval, value.params())?;
+    let opening = Opening::random(value.encode());
+    let (proof, commit_v) = IntervalProof::prove(transcript, points, &opening, interval)?;
+
+    Ok((proof, commit_v, opening))
+}
+
+pub fn verify<T: ValueEncoder>(transcript: &mut Transcript, points: &GlobalPoints, proof: &IntervalProof, commit_v: RistrettoPoint, interval: Range<T>) -> Result<(), ProofError> {
+    let interval = encode_interval(&interval, interval.start.params())?;
+
+    proof.verify(transcript, points, commit_v, interval)
+}
+
+pub fn decode_opening<T: ValueEncoder>(opening: &Opening, params: T::Params) -> T {
+    T::decode(opening.value(), params)
+}
+
+fn encode_interval<T: ValueEncoder>(interval: &Range<T>, params: T::Params) -> Result<Range<u64>, ProofError> {
+    if interval.start.params() != params || interval.end.params() != params {
+        return Err(ProofError::EncodingMismatch);
+    }
+
+    Ok(interval.start.encode()..interval.end.encode())
+}
+
+// Offset binary: flipping the sign bit keeps i64 ordering when read back as u64.
+fn offset_encode(value: i64) -> u64 {
+    (value as u64) ^ (1 << 63)
+}
+
+fn offset_decode(encoded: u64) -> i64 {
+    (encoded ^ (1 << 63)) as i64
+}
+
+impl ValueEncoder for u64 {
+    type Params = ();
+
+    fn params(&self) {}
+
+    fn encode(&self) -> u64 {
+        *self
+    }
+
+    fn decode(encoded: u64, _: ()) -> Self {
+        encoded
+    }
+}
+
+impl ValueEncoder for i64 {
+    type Params = ();
+
+    fn params(&self) {}
+
+    fn encode(&self) -> u64 {
+        offset_encode(*self)
+    }
+
+    fn decode(encoded: u64, _: ()) -> Self {
+        offset_decode(encoded)
+    }
+}
+
+impl Decimal {
+    pub fn new(mantissa: i64, scale: u32) -> Self {
+        Self {
+            mantissa,
+            scale,
+        }
+    }
+
+    // Same value with more fractional digits, so that bounds and value can share a scale.
+    pub fn rescale(&self, scale: u32) -> Option<Self> {
+        let factor = 10i64.checked_pow(scale.checked_sub(self.scale)?)?;
+
+        Some(Decimal::new(self.mantissa.checked_mul(factor)?, scale))
+    }
+
+    pub fn mantissa(&self) -> i64 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+}
+
+impl ValueEncoder for Decimal {
+    type Params = u32;
+
+    fn params(&self) -> u32 {
+        self.scale
+    }
+
+    fn encode(&self) -> u64 {
+        offset_encode(self.mantissa)
+    }
+
+    fn decode(encoded: u64, scale: u32) -> Self {
+        Decimal::new(offset_decode(encoded), scale)
+    }
+}
+
+impl Timestamp {
+    pub fn from_unix(secs: i64) -> Self {
+        Self { secs }
+    }
+
+    // Midnight UTC of a proleptic Gregorian date.
+    pub fn from_date(year: i64, month: u32, day: u32) -> Option<Self> {
+        if year.unsigned_abs() > 1 << 32 || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        let secs = days_from_civil(year, month, day) * 86_400;
+
+        Some(Timestamp::from_unix(secs))
+    }
+
+    pub fn unix(&self) -> i64 {
+        self.secs
+    }
+
+    pub fn to_date(&self) -> (i64, u32, u32) {
+        civil_from_days(self.secs.div_euclid(86_400))
+    }
+}
+
+impl ValueEncoder for Timestamp {
+    type Params = ();
+
+    fn params(&self) {}
+
+    fn encode(&self) -> u64 {
+        offset_encode(self.secs)
+    }
+
+    fn decode(encoded: u64, _: ()) -> Self {
+        Timestamp::from_unix(offset_decode(encoded))
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01, after Howard Hinnant's civil calendar algorithms.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - (era * 400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = ((153 * mp) + 2) / 5 + day as i64 - 1;
+    let doe = (yoe * 365) + (yoe / 4) - (yoe / 100) + doy;
+
+    (era * 146_097) + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - (era * 146_097);
+    let yoe = (doe - (doe / 1460) + (doe / 36_524) - (doe / 146_096)) / 365;
+    let doy = doe - ((365 * yoe) + (yoe / 4) - (yoe / 100));
+    let mp = ((5 * doy) + 2) / 153;
+    let day = (doy - ((153 * mp) + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + (era * 400)) + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prove_verify_round_trip() {
+        let points = GlobalPoints::gen_global(64);
+        let interval = Decimal::new(-500, 2)..Decimal::new(2500, 2);
+        let (proof, commit_v, opening) = prove(&mut Transcript::new(b"test"), &points, Decimal::new(1234, 2), interval).unwrap();
+
+        assert_eq!(verify(&mut Transcript::new(b"test"), &points, &proof, commit_v, Decimal::new(-500, 2)..Decimal::new(2500, 2)), Ok(()));
+        assert_eq!(decode_opening::<Decimal>(&opening, 2), Decimal::new(1234, 2));
+    }
+
+    #[test]
+    fn rejects_other_interval_or_scale() {
+        let points = GlobalPoints::gen_global(64);
+        let (proof, commit_v, _) = prove(&mut Transcript::new(b"test"), &points, -3i64, -10..10).unwrap();
+
+        assert!(verify(&mut Transcript::new(b"test"), &points, &proof, commit_v, -10i64..0).is_err());
+        assert!(verify(&mut Transcript::new(b"other"), &points, &proof, commit_v, -10i64..10).is_err());
+        assert!(matches!(prove(&mut Transcript::new(b"test"), &points, Decimal::new(1, 1), Decimal::new(0, 2)..Decimal::new(100, 2)), Err(ProofError::EncodingMismatch)));
+    }
+
+    #[test]
+    fn encodings_preserve_order() {
+        let values = [i64::MIN, -1, 0, 1, i64::MAX];
+        assert!(values.windows(2).all(|x| x[0].encode() < x[1].encode()));
+        assert!(values.iter().all(|x| i64::decode(x.encode(), ()) == *x));
+        assert_eq!(Decimal::new(15, 1).rescale(3), Some(Decimal::new(1500, 3)));
+        assert_eq!(Decimal::new(15, 3).rescale(1), None);
+    }
+
+    #[test]
+    fn dates_round_trip() {
+        let leap = Timestamp::from_date(2024, 2, 29).unwrap();
+        assert_eq!(leap.to_date(), (2024, 2, 29));
+        assert_eq!(Timestamp::from_date(1970, 1, 1), Some(Timestamp::from_unix(0)));
+        assert_eq!(Timestamp::from_date(1969, 12, 31).unwrap().unix(), -86_400);
+        assert_eq!(Timestamp::from_date(2023, 2, 29), None);
+    }
+}
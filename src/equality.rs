@@ -0,0 +1,193 @@
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use rand_core::OsRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ProofError;
+use crate::generator::GlobalPoints;
+use crate::prover::Opening;
+use crate::serialization::{write_points, write_scalars, Reader};
+use crate::transcript::Transcript;
+#[cfg(feature = "serde")]
+use crate::text::{hex_point, hex_scalar};
+
+// Value and blinding generators of a Pedersen commitment scheme, ours being (g_i, b_i).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommitmentBases {
+    g: RistrettoPoint,
+    h: RistrettoPoint,
+}
+
+// Sigma proof that C1 = v * G1 + r1 * H1 and C2 = v * G2 + r2 * H2 hide the same v.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EqualityProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_r1: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_r2: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_v: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_r1: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_r2: Scalar,
+}
+
+impl CommitmentBases {
+    pub fn new(g: RistrettoPoint, h: RistrettoPoint) -> Self {
+        Self { g, h }
+    }
+
+    pub fn from_points(points: &GlobalPoints) -> Self {
+        CommitmentBases::new(points.g_i(), points.b_i())
+    }
+
+    pub fn commit(&self, opening: &Opening) -> RistrettoPoint {
+        (Scalar::from(opening.value()) * self.g) + (opening.gamma() * self.h)
+    }
+
+    pub fn g(&self) -> RistrettoPoint {
+        self.g
+    }
+
+    pub fn h(&self) -> RistrettoPoint {
+        self.h
+    }
+}
+
+impl EqualityProof {
+    pub fn prove(transcript: &mut Transcript, [bases1, bases2]: [CommitmentBases; 2], [opening1, opening2]: [&Opening; 2]) -> Result<(Self, [RistrettoPoint; 2]), ProofError> {
+        if opening1.value() != opening2.value() {
+            return Err(ProofError::OpeningMismatch);
+        }
+        let commits = [bases1.commit(opening1), bases2.commit(opening2)];
+        append_statement(transcript, [bases1, bases2], commits);
+
+        let mut rng = OsRng;
+        let [k_v, k_r1, k_r2] = [(); 3].map(|_| Scalar::random(&mut rng));
+        let commit_r1 = (k_v * bases1.g) + (k_r1 * bases1.h);
+        let commit_r2 = (k_v * bases2.g) + (k_r2 * bases2.h);
+        transcript.append_point(b"R1", &commit_r1);
+        transcript.append_point(b"R2", &commit_r2);
+        let c = transcript.challenge_scalar(b"c");
+
+        Ok((Self {
+            commit_r1,
+            commit_r2,
+            pi_v: k_v + (c * Scalar::from(opening1.value())),
+            pi_r1: k_r1 + (c * opening1.gamma()),
+            pi_r2: k_r2 + (c * opening2.gamma()),
+        }, commits))
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, [bases1, bases2]: [CommitmentBases; 2], [commit1, commit2]: [RistrettoPoint; 2]) -> Result<(), ProofError> {
+        append_statement(transcript, [bases1, bases2], [commit1, commit2]);
+        transcript.append_point(b"R1", &self.commit_r1);
+        transcript.append_point(b"R2", &self.commit_r2);
+        let c = transcript.challenge_scalar(b"c");
+
+        let eqn1 = (self.pi_v * bases1.g) + (self.pi_r1 * bases1.h) == self.commit_r1 + (c * commit1);
+        let eqn2 = (self.pi_v * bases2.g) + (self.pi_r2 * bases2.h) == self.commit_r2 + (c * commit2);
+        if eqn1 && eqn2 {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    // R1, R2, pi_v, pi_r1, pi_r2
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_points(&mut bytes, &[self.commit_r1, self.commit_r2]);
+        write_scalars(&mut bytes, &[self.pi_v, self.pi_r1, self.pi_r2]);
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = Reader::new(bytes);
+        let proof = Self {
+            commit_r1: reader.read_point()?,
+            commit_r2: reader.read_point()?,
+            pi_v: reader.read_scalar()?,
+            pi_r1: reader.read_scalar()?,
+            pi_r2: reader.read_scalar()?,
+        };
+        reader.finish()?;
+
+        Ok(proof)
+    }
+
+    pub fn commit_r1(&self) -> RistrettoPoint {
+        self.commit_r1
+    }
+
+    pub fn commit_r2(&self) -> RistrettoPoint {
+        self.commit_r2
+    }
+
+    pub fn pi_v(&self) -> Scalar {
+        self.pi_v
+    }
+
+    pub fn pi_r1(&self) -> Scalar {
+        self.pi_r1
+    }
+
+    pub fn pi_r2(&self) -> Scalar {
+        self.pi_r2
+    }
+}
+
+fn append_statement(transcript: &mut Transcript, [bases1, bases2]: [CommitmentBases; 2], [commit1, commit2]: [RistrettoPoint; 2]) {
+    transcript.append_message(b"dom-sep", b"equality proof v1");
+    transcript.append_point(b"G1", &bases1.g);
+    transcript.append_point(b"H1", &bases1.h);
+    transcript.append_point(b"G2", &bases2.g);
+    transcript.append_point(b"H2", &bases2.h);
+    transcript.append_point(b"C1", &commit1);
+    transcript.append_point(b"C2", &commit2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bases() -> [CommitmentBases; 2] {
+        let points = GlobalPoints::gen_global(8);
+        let other = CommitmentBases::new(RistrettoPoint::random(&mut OsRng), RistrettoPoint::random(&mut OsRng));
+
+        [CommitmentBases::from_points(&points), other]
+    }
+
+    #[test]
+    fn prove_verify_round_trip() {
+        let bases = bases();
+        let (proof, commits) = EqualityProof::prove(&mut Transcript::new(b"test"), bases, [&Opening::random(7), &Opening::random(7)]).unwrap();
+
+        assert_eq!(proof.verify(&mut Transcript::new(b"test"), bases, commits), Ok(()));
+    }
+
+    #[test]
+    fn rejects_other_commitment_or_bases() {
+        let bases = bases();
+        let (proof, [commit1, commit2]) = EqualityProof::prove(&mut Transcript::new(b"test"), bases, [&Opening::random(7), &Opening::random(7)]).unwrap();
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), bases, [commit1, bases[1].commit(&Opening::random(8))]).is_err());
+        assert!(proof.verify(&mut Transcript::new(b"test"), [bases[1], bases[0]], [commit1, commit2]).is_err());
+        assert!(matches!(EqualityProof::prove(&mut Transcript::new(b"test"), bases, [&Opening::random(7), &Opening::random(8)]), Err(ProofError::OpeningMismatch)));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let bases = bases();
+        let (proof, commits) = EqualityProof::prove(&mut Transcript::new(b"test"), bases, [&Opening::random(7), &Opening::random(7)]).unwrap();
+        let bytes = proof.to_bytes();
+        let decoded = EqualityProof::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.verify(&mut Transcript::new(b"test"), bases, commits), Ok(()));
+        assert!(EqualityProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
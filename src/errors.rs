@@ -0,0 +1,85 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofError {
+    VerificationError,
+    InvalidGeneratorsLength,
+    MissingAssignment,
+    UnsatisfiedConstraint,
+    InvalidVariable,
+    InvalidBitsize,
+    ValueOutOfRange,
+    UnbalancedTransaction,
+    InvalidAggregation,
+    InvalidInterval,
+    EncodingMismatch,
+    OpeningMismatch,
+    Decode(DecodeError),
+}
+
+// Why a proof encoding was rejected. Every accepted proof has exactly one encoding, so
+// anything non-canonical or left over is an error rather than being normalised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    InvalidPoint,
+    IdentityPoint,
+    NonCanonicalScalar,
+    TrailingBytes,
+    InvalidLength,
+    RoundCountMismatch { expected: usize, found: usize },
+    UnknownMode(u8),
+    UnknownVersion(u8),
+    InvalidHex,
+    InvalidBase64,
+    InvalidArmor,
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofError::VerificationError => write!(f, "Proof verification failed"),
+            ProofError::InvalidGeneratorsLength => write!(f, "Not enough basis generators for the proof size"),
+            ProofError::MissingAssignment => write!(f, "Prover variable allocated without an assignment"),
+            ProofError::UnsatisfiedConstraint => write!(f, "Witness does not satisfy the constraint system"),
+            ProofError::InvalidVariable => write!(f, "Constraint refers to a variable that was never allocated"),
+            ProofError::InvalidBitsize => write!(f, "Bit width must be a power of two no larger than 64"),
+            ProofError::ValueOutOfRange => write!(f, "Value does not fit in the requested bit width"),
+            ProofError::UnbalancedTransaction => write!(f, "Inputs do not equal outputs plus fee"),
+            ProofError::InvalidAggregation => write!(f, "Every aggregated value needs exactly one bit width"),
+            ProofError::InvalidInterval => write!(f, "Interval must satisfy min < max"),
+            ProofError::EncodingMismatch => write!(f, "Value and interval bounds use different encodings"),
+            ProofError::OpeningMismatch => write!(f, "Opening does not match the supplied commitment"),
+            ProofError::Decode(err) => write!(f, "Proof bytes are malformed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
+impl From<DecodeError> for ProofError {
+    fn from(err: DecodeError) -> Self {
+        ProofError::Decode(err)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "input ended in the middle of an element"),
+            DecodeError::InvalidPoint => write!(f, "bytes are not a canonical compressed Ristretto point"),
+            DecodeError::IdentityPoint => write!(f, "identity point where a commitment is expected"),
+            DecodeError::NonCanonicalScalar => write!(f, "scalar is not reduced modulo the group order"),
+            DecodeError::TrailingBytes => write!(f, "unexpected bytes after the end of the proof"),
+            DecodeError::InvalidLength => write!(f, "length does not fit the proof layout"),
+            DecodeError::RoundCountMismatch { expected, found } => write!(f, "expected {} folding rounds for the bit width, found {}", expected, found),
+            DecodeError::UnknownMode(mode) => write!(f, "unknown proof mode {}", mode),
+            DecodeError::UnknownVersion(version) => write!(f, "unknown protocol version {}", version),
+            DecodeError::InvalidHex => write!(f, "text is not an even number of lowercase hex digits"),
+            DecodeError::InvalidBase64 => write!(f, "armored body is not canonical base64"),
+            DecodeError::InvalidArmor => write!(f, "armor lines or header do not match the expected proof type"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
@@ -0,0 +1,327 @@
+use std::array;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+
+use crate::bullerproof::InnerProductProof;
+use crate::errors::ProofError;
+use crate::generator::{n2_gen, powers_gen, GlobalPoints};
+use crate::operations::{invert, points_hadamard_multiply};
+use crate::prover::{blinding_openings, commit_asv, commit_t, t_coefficients, Opening, Salts};
+use crate::rangeproof::{self, check_bitsize};
+use crate::text::{from_hex, to_hex};
+use crate::transcript::Transcript;
+use crate::verifier::VerifierKey;
+
+// A range proof whose bit width is part of its type, so a RangeProof<32> can only come from a
+// Prover<32> and only be checked by a Verifier<32>. The prover keeps the bits, the blinding
+// vectors and l, r as [Scalar; BITS]; the encoded proof is the runtime-width
+// rangeproof::RangeProof, whose folding rounds stay on the heap since stable Rust cannot size
+// an array by log2(BITS).
+pub struct RangeProof<const BITS: usize> {
+    proof: rangeproof::RangeProof,
+}
+
+// Commits and proves for one bit width against a generator set checked once up front.
+pub struct Prover<'a, const BITS: usize> {
+    points: &'a GlobalPoints,
+}
+
+// Verifies proofs of one bit width, with the shape-dependent terms precomputed in a VerifierKey.
+pub struct Verifier<const BITS: usize> {
+    key: VerifierKey,
+}
+
+// A range proof with the width chosen at run time, kept next to the proof so prover and
+// verifier still cannot disagree on it.
+pub struct DynRangeProof {
+    bits: usize,
+    proof: rangeproof::RangeProof,
+}
+
+// Evaluated for every width a program uses, so e.g. Prover<12> is a compile error.
+struct Width<const BITS: usize>;
+
+impl<const BITS: usize> Width<BITS> {
+    const CHECK: () = assert!(BITS.is_power_of_two() && BITS <= 64, "Bit width must be a power of two no larger than 64");
+}
+
+impl<'a, const BITS: usize> Prover<'a, BITS> {
+    pub fn new(points: &'a GlobalPoints) -> Result<Self, ProofError> {
+        let () = Width::<BITS>::CHECK;
+        if points.g_basis().len() < BITS {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+
+        Ok(Self {
+            points,
+        })
+    }
+
+    // Shares the transcript and commitment steps with rangeproof::RangeProof::prove, so the two
+    // give the same proof for the same salts; only the vectors in between are arrays here.
+    pub fn prove(&self, transcript: &mut Transcript, v: u64, salt: Salts) -> Result<(RangeProof<BITS>, RistrettoPoint), ProofError> {
+        if BITS < 64 && v >> BITS != 0 {
+            return Err(ProofError::ValueOutOfRange);
+        }
+        let points = self.points;
+        let al: [Scalar; BITS] = array::from_fn(|i| Scalar::from((v >> i) & 1));
+        let ar: [Scalar; BITS] = array::from_fn(|i| al[i] - Scalar::ONE);
+        let [sl, sr] = salt.blinding_vectors(BITS).map(to_array::<BITS>);
+        let [commit_a, commit_s, commit_v] = commit_asv(points, v, [&al, &ar], [&sl, &sr], &salt);
+        let [y, z] = rangeproof::append_commitments(transcript, BITS, [commit_v, commit_a, commit_s]);
+
+        // l(X) = l0 + sl * X and r(X) = r0 + r1 * X, so t1 and t2 are the cross and square terms.
+        let [yn, n2] = [powers_gen(y, BITS), n2_gen(BITS)].map(to_array::<BITS>);
+        let z2 = z * z;
+        let l0: [Scalar; BITS] = array::from_fn(|i| al[i] - z);
+        let r0: [Scalar; BITS] = array::from_fn(|i| (yn[i] * (ar[i] + z)) + (z2 * n2[i]));
+        let r1: [Scalar; BITS] = array::from_fn(|i| yn[i] * sr[i]);
+        let [commit_t1, commit_t2] = commit_t(points, t_coefficients([&l0, &r0], [&sl, &r1]), &salt);
+        let u = rangeproof::append_t_commitments(transcript, [commit_t1, commit_t2]);
+
+        let l: [Scalar; BITS] = array::from_fn(|i| l0[i] + (sl[i] * u));
+        let r: [Scalar; BITS] = array::from_fn(|i| r0[i] + (r1[i] * u));
+        let tu = dot(&l, &r);
+        let [pi_t, pi_lr] = blinding_openings(&salt, z, u);
+        let q = points.mul_g_i(&rangeproof::append_openings(transcript, [tu, pi_t, pi_lr]));
+
+        let y_inv_h = points_hadamard_multiply(&powers_gen(invert(&y), BITS), &points.h_basis()[..BITS]);
+        let ipp = InnerProductProof::create(transcript, [points.g_basis()[..BITS].to_vec(), y_inv_h], q, [l.to_vec(), r.to_vec()]);
+        let proof = rangeproof::RangeProof::from_parts([commit_a, commit_s, commit_t1, commit_t2], [tu, pi_lr, pi_t], ipp);
+
+        Ok((RangeProof { proof }, commit_v))
+    }
+
+    // For commitments created elsewhere: only gamma is taken from the caller, the rest of the salts are fresh.
+    pub fn prove_opening(&self, transcript: &mut Transcript, opening: &Opening, commit_v: RistrettoPoint) -> Result<RangeProof<BITS>, ProofError> {
+        if opening.commit(self.points) != commit_v {
+            return Err(ProofError::OpeningMismatch);
+        }
+        let (proof, _) = self.prove(transcript, opening.value(), Salts::with_gamma(opening.gamma()))?;
+
+        Ok(proof)
+    }
+}
+
+impl<const BITS: usize> Verifier<BITS> {
+    pub fn new(points: &GlobalPoints) -> Result<Self, ProofError> {
+        let () = Width::<BITS>::CHECK;
+
+        Ok(Self {
+            key: VerifierKey::new(BITS, points)?,
+        })
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, proof: &RangeProof<BITS>, commit_v: RistrettoPoint) -> Result<(), ProofError> {
+        proof.proof.verify_with_key(transcript, &self.key, commit_v)
+    }
+
+    pub fn key(&self) -> &VerifierKey {
+        &self.key
+    }
+}
+
+impl<const BITS: usize> RangeProof<BITS> {
+    pub const BITS: usize = BITS;
+    pub const ROUNDS: usize = BITS.trailing_zeros() as usize;
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.proof.to_bytes()
+    }
+
+    // Unlike rangeproof::RangeProof::from_bytes the width comes from the type.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let () = Width::<BITS>::CHECK;
+
+        Ok(Self {
+            proof: rangeproof::RangeProof::from_bytes(bytes, BITS)?,
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, ProofError> {
+        RangeProof::from_bytes(&from_hex(hex)?)
+    }
+
+    pub fn proof(&self) -> &rangeproof::RangeProof {
+        &self.proof
+    }
+
+    pub fn into_dynamic(self) -> DynRangeProof {
+        DynRangeProof {
+            bits: BITS,
+            proof: self.proof,
+        }
+    }
+}
+
+impl DynRangeProof {
+    pub fn prove(transcript: &mut Transcript, points: &GlobalPoints, v: u64, bits: usize, salt: Salts) -> Result<(Self, RistrettoPoint), ProofError> {
+        let (proof, commit_v) = rangeproof::RangeProof::prove(transcript, points, v, bits, salt)?;
+
+        Ok((Self { bits, proof }, commit_v))
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint) -> Result<(), ProofError> {
+        self.proof.verify(transcript, points, commit_v, self.bits)
+    }
+
+    pub fn verify_with_key(&self, transcript: &mut Transcript, key: &VerifierKey, commit_v: RistrettoPoint) -> Result<(), ProofError> {
+        if key.range() != self.bits {
+            return Err(ProofError::InvalidBitsize);
+        }
+
+        self.proof.verify_with_key(transcript, key, commit_v)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.proof.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8], bits: usize) -> Result<Self, ProofError> {
+        check_bitsize(bits)?;
+
+        Ok(Self {
+            bits,
+            proof: rangeproof::RangeProof::from_bytes(bytes, bits)?,
+        })
+    }
+
+    // Check bits() first to keep the proof around when the width might not match.
+    pub fn into_fixed<const BITS: usize>(self) -> Result<RangeProof<BITS>, ProofError> {
+        if self.bits != BITS {
+            return Err(ProofError::InvalidBitsize);
+        }
+
+        Ok(RangeProof { proof: self.proof })
+    }
+
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    pub fn proof(&self) -> &rangeproof::RangeProof {
+        &self.proof
+    }
+}
+
+impl<const BITS: usize> From<RangeProof<BITS>> for DynRangeProof {
+    fn from(proof: RangeProof<BITS>) -> Self {
+        proof.into_dynamic()
+    }
+}
+
+fn to_array<const BITS: usize>(vector: Vec<Scalar>) -> [Scalar; BITS] {
+    vector.try_into().expect("vector built with BITS entries")
+}
+
+fn dot<const BITS: usize>(left: &[Scalar; BITS], right: &[Scalar; BITS]) -> Scalar {
+    left.iter().zip(right).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+    use crate::errors::DecodeError;
+
+    fn seeded_salts() -> Salts {
+        Salts::from_rng(&mut ChaCha20Rng::from_seed([7; 32]))
+    }
+
+    #[test]
+    fn prove_verify_round_trip() {
+        let points = GlobalPoints::gen_global(8);
+        let (proof, commit_v) = Prover::<8>::new(&points).unwrap().prove(&mut Transcript::new(b"test"), 200, Salts::init()).unwrap();
+        let decoded = RangeProof::<8>::from_hex(&proof.to_hex()).unwrap();
+
+        assert_eq!(Verifier::<8>::new(&points).unwrap().verify(&mut Transcript::new(b"test"), &decoded, commit_v), Ok(()));
+        assert_eq!(RangeProof::<8>::ROUNDS, 3);
+    }
+
+    #[test]
+    fn matches_runtime_prover() {
+        fn check<const BITS: usize>(points: &GlobalPoints) {
+            let v = u64::MAX >> (64 - BITS) >> 1;
+            let (fixed, commit_v) = Prover::<BITS>::new(points).unwrap().prove(&mut Transcript::new(b"test"), v, seeded_salts()).unwrap();
+            let (runtime, runtime_v) = rangeproof::RangeProof::prove(&mut Transcript::new(b"test"), points, v, BITS, seeded_salts()).unwrap();
+
+            assert_eq!((fixed.to_bytes(), commit_v), (runtime.to_bytes(), runtime_v), "width {}", BITS);
+        }
+
+        let points = GlobalPoints::gen_global(64);
+        check::<1>(&points);
+        check::<2>(&points);
+        check::<4>(&points);
+        check::<8>(&points);
+        check::<16>(&points);
+        check::<32>(&points);
+        check::<64>(&points);
+    }
+
+    #[test]
+    fn rejects_wrong_statement() {
+        let points = GlobalPoints::gen_global(8);
+        let prover = Prover::<8>::new(&points).unwrap();
+        let (proof, commit_v) = prover.prove(&mut Transcript::new(b"test"), 200, Salts::init()).unwrap();
+        let verifier = Verifier::<8>::new(&points).unwrap();
+
+        assert_eq!(verifier.verify(&mut Transcript::new(b"test"), &proof, commit_v + points.g_i()), Err(ProofError::VerificationError));
+        assert_eq!(verifier.verify(&mut Transcript::new(b"other"), &proof, commit_v), Err(ProofError::VerificationError));
+        assert_eq!(prover.prove(&mut Transcript::new(b"test"), 256, Salts::init()).err(), Some(ProofError::ValueOutOfRange));
+        assert_eq!(prover.prove_opening(&mut Transcript::new(b"test"), &Opening::random(200), commit_v).err(), Some(ProofError::OpeningMismatch));
+        assert_eq!(Prover::<16>::new(&points).err(), Some(ProofError::InvalidGeneratorsLength));
+        assert_eq!(Verifier::<16>::new(&points).err(), Some(ProofError::InvalidGeneratorsLength));
+    }
+
+    #[test]
+    fn proves_existing_commitment() {
+        let points = GlobalPoints::gen_global(8);
+        let opening = Opening::random(99);
+        let commit_v = opening.commit(&points);
+        let proof = Prover::<8>::new(&points).unwrap().prove_opening(&mut Transcript::new(b"test"), &opening, commit_v).unwrap();
+
+        assert_eq!(Verifier::<8>::new(&points).unwrap().verify(&mut Transcript::new(b"test"), &proof, commit_v), Ok(()));
+    }
+
+    #[test]
+    fn dynamic_round_trip() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, commit_v) = DynRangeProof::prove(&mut Transcript::new(b"test"), &points, 200, 8, Salts::init()).unwrap();
+        let decoded = DynRangeProof::from_bytes(&proof.to_bytes(), 8).unwrap();
+        let key = VerifierKey::new(8, &points).unwrap();
+
+        assert_eq!(decoded.bits(), 8);
+        assert_eq!(decoded.verify_with_key(&mut Transcript::new(b"test"), &key, commit_v), Ok(()));
+        let fixed = decoded.into_fixed::<8>().unwrap();
+        assert_eq!(DynRangeProof::from(fixed).verify(&mut Transcript::new(b"test"), &points, commit_v), Ok(()));
+    }
+
+    #[test]
+    fn dynamic_rejects_other_widths() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, commit_v) = DynRangeProof::prove(&mut Transcript::new(b"test"), &points, 200, 8, Salts::init()).unwrap();
+        let key = VerifierKey::new(16, &points).unwrap();
+
+        assert_eq!(proof.verify_with_key(&mut Transcript::new(b"test"), &key, commit_v), Err(ProofError::InvalidBitsize));
+        assert_eq!(proof.verify_with_key(&mut Transcript::new(b"test"), &VerifierKey::aggregated(&[4, 4], &points).unwrap(), commit_v), Err(ProofError::InvalidAggregation));
+        assert_eq!(proof.into_fixed::<16>().err(), Some(ProofError::InvalidBitsize));
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        let points = GlobalPoints::gen_global(16);
+        let (proof, _) = Prover::<8>::new(&points).unwrap().prove(&mut Transcript::new(b"test"), 200, Salts::init()).unwrap();
+        let bytes = proof.to_bytes();
+
+        assert_eq!(RangeProof::<16>::from_bytes(&bytes).err(), Some(DecodeError::RoundCountMismatch { expected: 4, found: 3 }.into()));
+        assert_eq!(RangeProof::<8>::from_bytes(&[bytes.as_slice(), &[0]].concat()).err(), Some(DecodeError::TrailingBytes.into()));
+        assert_eq!(DynRangeProof::from_bytes(&bytes, 12).err(), Some(ProofError::InvalidBitsize));
+    }
+}
@@ -1,73 +1,364 @@
 use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::ristretto::{RistrettoBasepointTable, RistrettoPoint};
 use rand_chacha::ChaCha20Rng;
 use rand::SeedableRng;
-use rand_core::OsRng;
+use rand_core::{CryptoRng, OsRng, RngCore};
 use sha2::{Sha512, Digest};
-use crate::operations::scalarize;
+use sha3::{Sha3_512, Shake256};
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use curve25519_dalek::constants::{RISTRETTO_BASEPOINT_COMPRESSED, RISTRETTO_BASEPOINT_POINT};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+use crate::logging::{event, Phase};
+use crate::metrics::count_ops;
+use crate::operations::{inner_product, multiscalar_mul};
+#[cfg(feature = "serde")]
+use crate::text::{hex_point, hex_points};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GlobalPoints {
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
     g_basis: Vec<RistrettoPoint>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
     h_basis: Vec<RistrettoPoint>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
     g_i: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
     b_i: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tables: Option<BasisTables>,
+}
+
+// Fixed-base tables for G, B and every basis point, built on request by GlobalPoints::with_tables.
+struct BasisTables {
+    g_i: RistrettoBasepointTable,
+    b_i: RistrettoBasepointTable,
+    g_basis: Vec<RistrettoBasepointTable>,
+    h_basis: Vec<RistrettoBasepointTable>,
+}
+
+// A generator together with its table, if one was built, for code that holds on to G or B
+// without the whole GlobalPoints.
+#[derive(Clone)]
+pub(crate) struct FixedBase {
+    point: RistrettoPoint,
+    table: Option<RistrettoBasepointTable>,
 }
 
 impl GlobalPoints {
     pub fn gen_global(range: usize) -> Self {
+        GlobalPoints::from_rng(range, &mut OsRng)
+    }
+
+    // G and B from the caller's rng, the bases are always derived from the fixed seed.
+    pub fn from_rng<R: RngCore + CryptoRng>(range: usize, rng: &mut R) -> Self {
+        let _phase = Phase::enter("generators", range);
         let seed = b"G and H basis seed";
         let g_basis = gen_basis_vectors(range, seed, "g_basis");
         let h_basis = gen_basis_vectors(range, seed, "h_basis");
     
-        let mut rng = OsRng;
-        let g_i = RistrettoPoint::random(&mut rng);
-        let b_i = RistrettoPoint::random(&mut rng);
-        println!("Generated G_basis, H_basis, G, B");
+        let g_i = RistrettoPoint::random(rng);
+        let b_i = RistrettoPoint::random(rng);
 
         Self {
             g_basis,
             h_basis,
             g_i,
             b_i,
+            tables: None,
+        }
+    }
+
+    // Same generators as the dalek-cryptography bulletproofs crate for a single party:
+    // PedersenGens::default() for G and B, BulletproofGens::new(capacity, 1) for the bases.
+    pub fn gen_dalek(capacity: usize) -> Self {
+        let _phase = Phase::enter("dalek generators", capacity);
+        let g_basis = gen_chain(b"G\x00\x00\x00\x00", capacity);
+        let h_basis = gen_chain(b"H\x00\x00\x00\x00", capacity);
+        let g_i = RISTRETTO_BASEPOINT_POINT;
+        let b_i = RistrettoPoint::hash_from_bytes::<Sha3_512>(RISTRETTO_BASEPOINT_COMPRESSED.as_bytes());
+
+        Self {
+            g_basis,
+            h_basis,
+            g_i,
+            b_i,
+            tables: None,
+        }
+    }
+
+    // Precomputes a fixed-base table for G, B and every basis point. Each table is about 30 KB, so
+    // this pays off for a long-lived generator set that commits to many values.
+    pub fn with_tables(mut self) -> Self {
+        let _phase = Phase::enter("generator tables", self.g_basis.len());
+        self.tables = Some(BasisTables {
+            g_i: RistrettoBasepointTable::create(&self.g_i),
+            b_i: RistrettoBasepointTable::create(&self.b_i),
+            g_basis: self.g_basis.iter().map(RistrettoBasepointTable::create).collect(),
+            h_basis: self.h_basis.iter().map(RistrettoBasepointTable::create).collect(),
+        });
+
+        self
+    }
+
+    pub fn has_tables(&self) -> bool {
+        self.tables.is_some()
+    }
+
+    pub fn mul_g_i(&self, scalar: &Scalar) -> RistrettoPoint {
+        count_ops(1, 0, 0);
+        match &self.tables {
+            Some(tables) => &tables.g_i * scalar,
+            None => scalar * self.g_i,
+        }
+    }
+
+    pub fn mul_b_i(&self, scalar: &Scalar) -> RistrettoPoint {
+        count_ops(1, 0, 0);
+        match &self.tables {
+            Some(tables) => &tables.b_i * scalar,
+            None => scalar * self.b_i,
+        }
+    }
+
+    // value * G + blinding * B
+    pub fn commit(&self, value: &Scalar, blinding: &Scalar) -> RistrettoPoint {
+        count_ops(0, 1, 0);
+        self.mul_g_i(value) + self.mul_b_i(blinding)
+    }
+
+    // <left, G> + <right, H> + blinding * B, the shape of the A and S commitments. Without
+    // tables this is a single multiscalar multiplication. Panics if there are more scalars than
+    // bases, which callers rule out with check_range.
+    pub fn vector_commit(&self, [left, right]: [&[Scalar]; 2], blinding: &Scalar) -> RistrettoPoint {
+        let [g_len, h_len] = [self.basis_len(left), self.basis_len(right)];
+        match &self.tables {
+            Some(tables) => table_product(left, &tables.g_basis[..g_len]) + table_product(right, &tables.h_basis[..h_len]) + self.mul_b_i(blinding),
+            None => multiscalar_mul(&[(left, &self.g_basis[..g_len]), (right, &self.h_basis[..h_len]), (std::slice::from_ref(blinding), std::slice::from_ref(&self.b_i))]),
         }
     }
 
-    pub fn g_basis(&self) -> Vec<RistrettoPoint> {
-        self.g_basis.clone()
+    // <scalars, G basis> over the first scalars.len() bases. Panics if there are more scalars than bases.
+    pub fn g_basis_product(&self, scalars: &[Scalar]) -> RistrettoPoint {
+        let len = self.basis_len(scalars);
+        match &self.tables {
+            Some(tables) => table_product(scalars, &tables.g_basis[..len]),
+            None => inner_product(scalars, &self.g_basis[..len]),
+        }
     }
 
-    pub fn h_basis(&self) -> Vec<RistrettoPoint> {
-        self.h_basis.clone()
+    pub fn h_basis_product(&self, scalars: &[Scalar]) -> RistrettoPoint {
+        let len = self.basis_len(scalars);
+        match &self.tables {
+            Some(tables) => table_product(scalars, &tables.h_basis[..len]),
+            None => inner_product(scalars, &self.h_basis[..len]),
+        }
+    }
+
+    fn basis_len(&self, scalars: &[Scalar]) -> usize {
+        assert!(scalars.len() <= self.g_basis.len(), "{} scalars for {} basis generators", scalars.len(), self.g_basis.len());
+
+        scalars.len()
+    }
+
+    pub(crate) fn fixed_g_i(&self) -> FixedBase {
+        FixedBase {
+            point: self.g_i,
+            table: self.tables.as_ref().map(|x| x.g_i.clone()),
+        }
+    }
+
+    pub(crate) fn fixed_b_i(&self) -> FixedBase {
+        FixedBase {
+            point: self.b_i,
+            table: self.tables.as_ref().map(|x| x.b_i.clone()),
+        }
+    }
+
+    pub fn g_basis(&self) -> &[RistrettoPoint] {
+        &self.g_basis
+    }
+
+    pub fn h_basis(&self) -> &[RistrettoPoint] {
+        &self.h_basis
     }
 
     pub fn g_i(&self) -> RistrettoPoint {
-        self.g_i.clone()
+        self.g_i
     }
 
     pub fn b_i(&self) -> RistrettoPoint {
-        self.b_i.clone()
+        self.b_i
+    }
+
+    // Identifies a generator set: Sha512 over G, B and both bases, truncated to 32 bytes.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Sha512::new();
+        Digest::update(&mut hasher, b"generators fingerprint v1");
+        Digest::update(&mut hasher, (self.g_basis.len() as u64).to_le_bytes());
+        for point in [self.g_i, self.b_i].iter().chain(self.g_basis.iter()).chain(self.h_basis.iter()) {
+            Digest::update(&mut hasher, point.compress().as_bytes());
+        }
+
+        hasher.finalize()[..32].try_into().expect("Sha512 output is 64 bytes")
     }
 }
 
+impl FixedBase {
+    // A base without a table, for points that do not come from a GlobalPoints.
+    pub(crate) fn plain(point: RistrettoPoint) -> Self {
+        Self { point, table: None }
+    }
+
+    pub(crate) fn has_table(&self) -> bool {
+        self.table.is_some()
+    }
+
+    pub(crate) fn mul(&self, scalar: &Scalar) -> RistrettoPoint {
+        count_ops(1, 0, 0);
+        match &self.table {
+            Some(table) => table * scalar,
+            None => scalar * self.point,
+        }
+    }
+
+    pub(crate) fn point(&self) -> RistrettoPoint {
+        self.point
+    }
+}
+
+fn table_product(scalars: &[Scalar], tables: &[RistrettoBasepointTable]) -> RistrettoPoint {
+    assert_eq!(scalars.len(), tables.len(), "one basis table per scalar");
+    count_ops(scalars.len(), scalars.len(), 0);
+    scalars.iter().zip(tables.iter()).map(|(scalar, table)| table * scalar).sum()
+}
+
 pub fn gen_basis_vectors(count: usize, seed: &[u8], domain: &str) -> Vec<RistrettoPoint> {
     let mut rng = ChaCha20Rng::from_seed(
-        Sha512::digest(&[seed, domain.as_bytes()].concat()).as_slice()[..32]
+        Sha512::digest([seed, domain.as_bytes()].concat()).as_slice()[..32]
             .try_into()
             .expect("Hash output must fit in 32 bytes"),
     );
 
-    println!("Basis vectors generated");
+    event!(domain, count, "basis vectors generated");
 
     (0..count)
         .map(|_| RistrettoPoint::random(&mut rng))
         .collect()
 }
 
-pub fn gen_scalars(count: usize, y: u64) -> Vec<Scalar> {
-    let mut rng = ChaCha20Rng::seed_from_u64(y);
-    (0..count).map(|_| Scalar::random(&mut rng)).collect()
+// SHAKE256 generator chain as in the bulletproofs crate: every point is taken from 64 bytes of XOF output.
+pub fn gen_chain(label: &[u8], count: usize) -> Vec<RistrettoPoint> {
+    let mut shake = Shake256::default();
+    shake.update(b"GeneratorsChain");
+    shake.update(label);
+    let mut reader = shake.finalize_xof();
+
+    (0..count)
+        .map(|_| {
+            let mut uniform_bytes = [0u8; 64];
+            reader.read(&mut uniform_bytes);
+            RistrettoPoint::from_uniform_bytes(&uniform_bytes)
+        })
+        .collect()
 }
 
 pub fn n2_gen(range: usize) -> Vec<Scalar> {
-    scalarize(&mut vec![2i64; range].iter().enumerate().map(|(x,i)| i.pow(x as u32)).collect())
+    powers_gen(Scalar::from(2u8), range)
+}
+
+pub fn powers_gen(x: Scalar, count: usize) -> Vec<Scalar> {
+    let mut power = Scalar::ONE;
+    (0..count).map(|_| {
+        let current = power;
+        power *= x;
+        current
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregation::AggregatedRangeProof;
+    use crate::bulletproofs_plus::BulletproofsPlusProof;
+    use crate::errors::ProofError;
+    use crate::prover::{Opening, Salts};
+    use crate::rangeproof::RangeProof;
+    use crate::transcript::Transcript;
+
+    fn scalars(count: usize) -> Vec<Scalar> {
+        (0..count).map(|_| Scalar::random(&mut OsRng)).collect()
+    }
+
+    #[test]
+    fn tables_match_plain_multiplication() {
+        let plain = GlobalPoints::gen_global(8);
+        let tabled = GlobalPoints::from_rng(8, &mut ChaCha20Rng::from_seed([0; 32])).with_tables();
+        let seeded = GlobalPoints::from_rng(8, &mut ChaCha20Rng::from_seed([0; 32]));
+        let [left, right, short] = [scalars(8), scalars(8), scalars(3)];
+        let blinding = Scalar::random(&mut OsRng);
+
+        assert!(tabled.has_tables() && !plain.has_tables());
+        assert_eq!(tabled.fingerprint(), seeded.fingerprint());
+        assert_eq!(tabled.vector_commit([&left, &right], &blinding), seeded.vector_commit([&left, &right], &blinding));
+        assert_eq!(tabled.vector_commit([&short, &right], &blinding), seeded.vector_commit([&short, &right], &blinding));
+        assert_eq!(tabled.commit(&left[0], &blinding), seeded.commit(&left[0], &blinding));
+        assert_eq!(tabled.g_basis_product(&short), inner_product(&short, &seeded.g_basis()[..3]));
+        assert_eq!(tabled.h_basis_product(&right), inner_product(&right, seeded.h_basis()));
+        assert_eq!(tabled.fixed_b_i().mul(&blinding), seeded.fixed_b_i().mul(&blinding));
+        assert_eq!(tabled.fixed_g_i().point(), seeded.g_i());
+    }
+
+    #[test]
+    fn proofs_with_tables_verify_without() {
+        let tabled = GlobalPoints::from_rng(8, &mut ChaCha20Rng::from_seed([1; 32])).with_tables();
+        let plain = GlobalPoints::from_rng(8, &mut ChaCha20Rng::from_seed([1; 32]));
+        let (proof, commit_v) = RangeProof::prove(&mut Transcript::new(b"test"), &tabled, 200, 8, Salts::init()).unwrap();
+
+        assert_eq!(proof.verify(&mut Transcript::new(b"test"), &plain, commit_v, 8), Ok(()));
+        assert_eq!(proof.verify(&mut Transcript::new(b"test"), &GlobalPoints::gen_global(8).with_tables(), commit_v, 8), Err(ProofError::VerificationError));
+    }
+
+    #[test]
+    fn aggregated_and_plus_proofs_with_tables_verify_without() {
+        let tabled = GlobalPoints::from_rng(16, &mut ChaCha20Rng::from_seed([2; 32])).with_tables();
+        let plain = GlobalPoints::from_rng(16, &mut ChaCha20Rng::from_seed([2; 32]));
+        let (aggregated, commits) = AggregatedRangeProof::prove(&mut Transcript::new(b"test"), &tabled, &[Opening::random(200), Opening::random(3)], &[8, 2]).unwrap();
+        let (plus, commit_v) = BulletproofsPlusProof::prove(&mut Transcript::new(b"test"), &tabled, &Opening::random(200), 8).unwrap();
+
+        assert_eq!(aggregated.verify(&mut Transcript::new(b"test"), &plain, &commits, &[8, 2]), Ok(()));
+        assert_eq!(plus.verify(&mut Transcript::new(b"test"), &plain, commit_v, 8), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "9 scalars for 8 basis generators")]
+    fn rejects_more_scalars_than_tables() {
+        GlobalPoints::gen_global(8).with_tables().g_basis_product(&scalars(9));
+    }
+
+    #[test]
+    #[should_panic(expected = "9 scalars for 8 basis generators")]
+    fn rejects_more_scalars_than_bases() {
+        GlobalPoints::gen_global(8).vector_commit([&scalars(8), &scalars(9)], &Scalar::ONE);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let points = GlobalPoints::gen_global(8);
+        let decoded: GlobalPoints = serde_json::from_str(&serde_json::to_string(&points).unwrap()).unwrap();
+
+        assert_eq!(decoded.fingerprint(), points.fingerprint());
+        assert!(!decoded.has_tables());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_identity_generator() {
+        let mut json = serde_json::to_value(GlobalPoints::gen_global(8)).unwrap();
+        json["b_i"] = "00".repeat(32).into();
+
+        assert!(serde_json::from_value::<GlobalPoints>(json).is_err());
+    }
 }
@@ -0,0 +1,333 @@
+use std::fmt;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+
+use crate::aggregation::{AggregatedRangeProof, AGGREGATED_MODE};
+use crate::bullerproof::InnerProductProof;
+use crate::errors::{DecodeError, ProofError};
+use crate::generator::GlobalPoints;
+use crate::rangeproof::{check_bitsize, read_header, ProofMode, RangeProofVariant, PROTOCOL_VERSION};
+use crate::reciprocal::folding_rounds;
+use crate::serialization::Reader;
+use crate::text::to_hex;
+
+const WIDTHS: [usize; 7] = [1, 2, 4, 8, 16, 32, 64];
+
+// Decoded contents of a serialized RangeProofVariant or AggregatedRangeProof. Building it checks
+// the structure of the proof (version, mode tag, canonical points and scalars, round count,
+// length) but runs no verification. An aggregated proof is a Bulletproof over `parties` values
+// and `bits` holds the width of each; otherwise `bits` lists the widths the proof may have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofSummary {
+    version: u8,
+    mode: ProofMode,
+    aggregated: bool,
+    parties: usize,
+    bits: Vec<usize>,
+    commitments: Vec<(&'static str, [u8; 32])>,
+    rounds: Vec<[[u8; 32]; 2]>,
+    scalars: Vec<(&'static str, [u8; 32])>,
+    size: usize,
+    fingerprint: Option<[u8; 32]>,
+}
+
+// Decodes a proof produced by RangeProofVariant::to_bytes or AggregatedRangeProof::to_bytes.
+// Without a bit width every width consistent with the length is reported; the reciprocal mode
+// pads to a power of two, so several widths can share one encoding. Aggregated proofs carry
+// their widths, so `range` is not used for them.
+pub fn inspect(bytes: &[u8], range: Option<usize>) -> Result<ProofSummary, ProofError> {
+    let (tag, body) = read_header(bytes)?;
+    let mode = match tag {
+        0 => ProofMode::Bulletproof,
+        1 => ProofMode::BulletproofsPlus,
+        2 => ProofMode::Reciprocal,
+        AGGREGATED_MODE => return inspect_aggregated(bytes),
+        _ => return Err(DecodeError::UnknownMode(tag).into()),
+    };
+    let bits = match range {
+        Some(range) => vec![range],
+        None => {
+            let [head, tail] = layout(mode);
+            let rounds = Reader::new(body.get(head..).ok_or(DecodeError::UnexpectedEnd)?).rounds(tail)?;
+            WIDTHS.into_iter().filter(|&x| rounds_for(mode, x) == Ok(rounds)).collect()
+        }
+    };
+    let range = *bits.first().ok_or(DecodeError::InvalidLength)?;
+
+    let (commitments, ipp, scalars) = match RangeProofVariant::from_bytes(bytes, range)? {
+        RangeProofVariant::Bulletproof(proof) => (
+            vec![("A", proof.commit_a()), ("S", proof.commit_s()), ("T1", proof.commit_t1()), ("T2", proof.commit_t2())],
+            RoundPoints::ipp(proof.ipp()),
+            vec![("t", proof.tu()), ("pi_t", proof.pi_t()), ("pi_lr", proof.pi_lr()), ("a", proof.ipp().a()), ("b", proof.ipp().b())],
+        ),
+        RangeProofVariant::BulletproofsPlus(proof) => (
+            vec![("A", proof.commit_a()), ("A'", proof.wip().commit_a()), ("B'", proof.wip().commit_b())],
+            RoundPoints { l_vec: proof.wip().l_vec(), r_vec: proof.wip().r_vec() },
+            vec![("r'", proof.wip().pi_r()), ("s'", proof.wip().pi_s()), ("d'", proof.wip().pi_d())],
+        ),
+        RangeProofVariant::Reciprocal(proof) => (
+            vec![("D", proof.commit_d()), ("R", proof.commit_r()), ("S", proof.commit_s()), ("T1", proof.commit_t1()), ("T2", proof.commit_t2())],
+            RoundPoints::ipp(proof.ipp()),
+            vec![("t", proof.tu()), ("pi_t", proof.pi_t()), ("pi_lr", proof.pi_lr()), ("a", proof.ipp().a()), ("b", proof.ipp().b())],
+        ),
+    };
+
+    Ok(ProofSummary::new(mode, bits, commitments, ipp, scalars, bytes.len()))
+}
+
+fn inspect_aggregated(bytes: &[u8]) -> Result<ProofSummary, ProofError> {
+    let proof = AggregatedRangeProof::from_bytes(bytes)?;
+    let mut summary = ProofSummary::new(
+        ProofMode::Bulletproof,
+        proof.ranges().to_vec(),
+        vec![("A", proof.commit_a()), ("S", proof.commit_s()), ("T1", proof.commit_t1()), ("T2", proof.commit_t2())],
+        RoundPoints::ipp(proof.ipp()),
+        vec![("t", proof.tu()), ("pi_t", proof.pi_t()), ("pi_lr", proof.pi_lr()), ("a", proof.ipp().a()), ("b", proof.ipp().b())],
+        bytes.len(),
+    );
+    summary.aggregated = true;
+    summary.parties = proof.ranges().len();
+
+    Ok(summary)
+}
+
+impl ProofSummary {
+    fn new(mode: ProofMode, bits: Vec<usize>, commitments: Vec<(&'static str, RistrettoPoint)>, ipp: RoundPoints, scalars: Vec<(&'static str, Scalar)>, size: usize) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            mode,
+            aggregated: false,
+            parties: 1,
+            bits,
+            commitments: commitments.into_iter().map(|(name, point)| (name, point.compress().to_bytes())).collect(),
+            rounds: ipp.l_vec.iter().zip(ipp.r_vec.iter()).map(|(left, right)| [left.compress().to_bytes(), right.compress().to_bytes()]).collect(),
+            scalars: scalars.into_iter().map(|(name, scalar)| (name, scalar.to_bytes())).collect(),
+            size,
+            fingerprint: None,
+        }
+    }
+
+    // Adds the value commitment the proof was sent with, rejecting non-canonical or identity encodings.
+    pub fn with_commitment(mut self, commit_v: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = Reader::new(commit_v);
+        let point = reader.read_point()?;
+        reader.finish()?;
+        self.commitments.push(("V", point.compress().to_bytes()));
+
+        Ok(self)
+    }
+
+    // Records which generators the proof is checked against and that there are enough of them.
+    pub fn with_generators(mut self, points: &GlobalPoints) -> Result<Self, ProofError> {
+        if points.g_basis().len() < self.generators_needed() {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        self.fingerprint = Some(points.fingerprint());
+
+        Ok(self)
+    }
+
+    // Basis generators a verifier needs for this proof.
+    pub fn generators_needed(&self) -> usize {
+        1 << self.rounds.len()
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn mode(&self) -> ProofMode {
+        self.mode
+    }
+
+    pub fn is_aggregated(&self) -> bool {
+        self.aggregated
+    }
+
+    pub fn parties(&self) -> usize {
+        self.parties
+    }
+
+    pub fn bits(&self) -> Vec<usize> {
+        self.bits.clone()
+    }
+
+    pub fn commitments(&self) -> Vec<(&'static str, [u8; 32])> {
+        self.commitments.clone()
+    }
+
+    pub fn rounds(&self) -> Vec<[[u8; 32]; 2]> {
+        self.rounds.clone()
+    }
+
+    pub fn scalars(&self) -> Vec<(&'static str, [u8; 32])> {
+        self.scalars.clone()
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn fingerprint(&self) -> Option<[u8; 32]> {
+        self.fingerprint
+    }
+}
+
+impl fmt::Display for ProofSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bits: Vec<String> = self.bits.iter().map(|x| x.to_string()).collect();
+        writeln!(f, "version:     {}", self.version)?;
+        if self.aggregated {
+            writeln!(f, "mode:        {:?}, aggregated", self.mode)?;
+            writeln!(f, "parties:     {}", self.parties)?;
+            writeln!(f, "bit widths:  {}", bits.join(", "))?;
+        } else {
+            writeln!(f, "mode:        {:?}", self.mode)?;
+            writeln!(f, "parties:     {}", self.parties)?;
+            writeln!(f, "bit width:   {}", bits.join(" or "))?;
+        }
+        writeln!(f, "size:        {} bytes", self.size)?;
+        match self.fingerprint {
+            Some(fingerprint) => writeln!(f, "generators:  {}", to_hex(&fingerprint))?,
+            None => writeln!(f, "generators:  not given")?,
+        }
+        for (name, point) in &self.commitments {
+            writeln!(f, "{:<12} {}", format!("{}:", name), to_hex(point))?;
+        }
+        for (i, [left, right]) in self.rounds.iter().enumerate() {
+            writeln!(f, "{:<12} {}", format!("L[{}]:", i), to_hex(left))?;
+            writeln!(f, "{:<12} {}", format!("R[{}]:", i), to_hex(right))?;
+        }
+        for (name, scalar) in &self.scalars {
+            writeln!(f, "{:<12} {}", format!("{}:", name), to_hex(scalar))?;
+        }
+
+        Ok(())
+    }
+}
+
+struct RoundPoints {
+    l_vec: Vec<RistrettoPoint>,
+    r_vec: Vec<RistrettoPoint>,
+}
+
+impl RoundPoints {
+    fn ipp(ipp: &InnerProductProof) -> Self {
+        Self {
+            l_vec: ipp.l_vec(),
+            r_vec: ipp.r_vec(),
+        }
+    }
+}
+
+// Bytes before the folding rounds and after them, for each mode.
+fn layout(mode: ProofMode) -> [usize; 2] {
+    match mode {
+        ProofMode::Bulletproof => [7 * 32, 64],
+        ProofMode::BulletproofsPlus => [32, 160],
+        ProofMode::Reciprocal => [8 * 32, 64],
+    }
+}
+
+fn rounds_for(mode: ProofMode, range: usize) -> Result<usize, ProofError> {
+    match mode {
+        ProofMode::Bulletproof | ProofMode::BulletproofsPlus => {
+            check_bitsize(range)?;
+
+            Ok(range.trailing_zeros() as usize)
+        }
+        ProofMode::Reciprocal => folding_rounds(range),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::Opening;
+    use crate::transcript::Transcript;
+
+    fn proof_bytes(mode: ProofMode, points: &GlobalPoints) -> (Vec<u8>, RistrettoPoint) {
+        let (proof, commit_v) = RangeProofVariant::prove(mode, &mut Transcript::new(b"test"), points, &Opening::random(200), 8).unwrap();
+
+        (proof.to_bytes(), commit_v)
+    }
+
+    #[test]
+    fn summarises_every_mode() {
+        let points = GlobalPoints::gen_global(32);
+        for (mode, commitments, rounds) in [(ProofMode::Bulletproof, 4, 3), (ProofMode::BulletproofsPlus, 3, 3), (ProofMode::Reciprocal, 5, 5)] {
+            let (bytes, _) = proof_bytes(mode, &points);
+            let summary = inspect(&bytes, Some(8)).unwrap();
+
+            assert_eq!((summary.version(), summary.mode(), summary.parties()), (PROTOCOL_VERSION, mode, 1));
+            assert!(!summary.is_aggregated());
+            assert_eq!(summary.bits(), vec![8]);
+            assert_eq!(summary.commitments().len(), commitments);
+            assert_eq!(summary.rounds().len(), rounds);
+            assert_eq!(summary.size(), bytes.len());
+        }
+    }
+
+    #[test]
+    fn summarises_aggregated_proofs() {
+        let points = GlobalPoints::gen_global(32);
+        let openings = [Opening::random(200), Opening::random(3), Opening::random(40000)];
+        let (proof, _) = AggregatedRangeProof::prove(&mut Transcript::new(b"test"), &points, &openings, &[8, 2, 16]).unwrap();
+        let summary = inspect(&proof.to_bytes(), None).unwrap();
+
+        assert!(summary.is_aggregated());
+        assert_eq!((summary.version(), summary.mode(), summary.parties()), (PROTOCOL_VERSION, ProofMode::Bulletproof, 3));
+        assert_eq!(summary.bits(), vec![8, 2, 16]);
+        assert_eq!((summary.rounds().len(), summary.generators_needed()), (5, 32));
+        assert!(summary.to_string().contains("bit widths:  8, 2, 16"));
+    }
+
+    #[test]
+    fn infers_bit_width_from_length() {
+        let points = GlobalPoints::gen_global(32);
+        let (bytes, _) = proof_bytes(ProofMode::Bulletproof, &points);
+
+        assert_eq!(inspect(&bytes, None).unwrap().bits(), vec![8]);
+    }
+
+    #[test]
+    fn rejects_malformed_proofs() {
+        let points = GlobalPoints::gen_global(32);
+        let (bytes, _) = proof_bytes(ProofMode::Bulletproof, &points);
+        let mut unknown = bytes.clone();
+        unknown[1] = 7;
+        let mut version = bytes.clone();
+        version[0] = 2;
+
+        assert_eq!(inspect(&[], None), Err(DecodeError::UnexpectedEnd.into()));
+        assert_eq!(inspect(&unknown, None), Err(DecodeError::UnknownMode(7).into()));
+        assert_eq!(inspect(&version, None), Err(DecodeError::UnknownVersion(2).into()));
+        assert!(inspect(&bytes[..bytes.len() - 1], Some(8)).is_err());
+        assert!(inspect(&bytes, Some(16)).is_err());
+    }
+
+    #[test]
+    fn adds_commitment_and_generators() {
+        let points = GlobalPoints::gen_global(32);
+        let (bytes, commit_v) = proof_bytes(ProofMode::Bulletproof, &points);
+        let summary = inspect(&bytes, Some(8)).unwrap()
+            .with_commitment(commit_v.compress().as_bytes()).unwrap()
+            .with_generators(&points).unwrap();
+
+        assert_eq!(summary.commitments().last(), Some(&("V", commit_v.compress().to_bytes())));
+        assert_eq!(summary.fingerprint(), Some(points.fingerprint()));
+        assert!(summary.to_string().contains(&to_hex(&points.fingerprint())));
+    }
+
+    #[test]
+    fn rejects_bad_commitment_and_short_generators() {
+        let points = GlobalPoints::gen_global(32);
+        let (bytes, _) = proof_bytes(ProofMode::Reciprocal, &points);
+        let summary = inspect(&bytes, Some(8)).unwrap();
+
+        assert_eq!(summary.generators_needed(), 32);
+        assert_eq!(summary.clone().with_commitment(&[0u8; 32]), Err(DecodeError::IdentityPoint.into()));
+        assert_eq!(summary.with_generators(&GlobalPoints::gen_global(16)), Err(ProofError::InvalidGeneratorsLength));
+    }
+}
@@ -0,0 +1,457 @@
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::Identity;
+use merlin::Transcript;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{CryptoRng, OsRng, RngCore};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ProofError;
+use crate::generator::{n2_gen, powers_gen, GlobalPoints};
+use crate::logging::event;
+use crate::operations::{inner_product, points_hadamard_multiply, scalarize, vec_scalar_mul, vector_add};
+use crate::prover::{ASVcommitment, Opening};
+use crate::serialization::{write_points, write_scalars, Reader};
+use crate::text::{armor, dearmor, from_hex, to_hex};
+#[cfg(feature = "serde")]
+use crate::text::{hex_point, hex_points, hex_scalar};
+
+// Single-value range proof in the format of the dalek-cryptography bulletproofs crate:
+// merlin transcript, the same labels and challenge order, half-split inner-product folding
+// and the same byte encoding. Use GlobalPoints::gen_dalek for the generators.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CompatRangeProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_a: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_s: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_t1: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_t2: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    t_x: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    t_x_blinding: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    e_blinding: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
+    l_vec: Vec<RistrettoPoint>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
+    r_vec: Vec<RistrettoPoint>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    a: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    b: Scalar,
+}
+
+impl CompatRangeProof {
+    pub fn prove(transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, range: usize) -> Result<(Self, RistrettoPoint), ProofError> {
+        CompatRangeProof::prove_with_rng(transcript, points, opening, range, &mut OsRng)
+    }
+
+    // Draws randomness in the same order as RangeProof::prove_single_with_rng, so a seeded rng
+    // reproduces the other implementation's proof byte for byte.
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, range: usize, rng: &mut R) -> Result<(Self, RistrettoPoint), ProofError> {
+        check_compat_range(points, range)?;
+        if range < 64 && opening.value() >> range != 0 {
+            return Err(ProofError::ValueOutOfRange);
+        }
+        let commit_v = opening.commit(points);
+        let g_basis = points.g_basis()[..range].to_vec();
+        let h_basis = points.h_basis()[..range].to_vec();
+
+        let [mut al, mut ar] = ASVcommitment::compute_al_ar(opening.value(), range);
+        let [al, ar] = [scalarize(&mut al), scalarize(&mut ar)];
+        let a_blinding = Scalar::random(rng);
+        let commit_a = inner_product(&al, &g_basis) + inner_product(&ar, &h_basis) + (a_blinding * points.b_i());
+        let s_blinding = Scalar::random(rng);
+        let sl: Vec<Scalar> = (0..range).map(|_| Scalar::random(rng)).collect();
+        let sr: Vec<Scalar> = (0..range).map(|_| Scalar::random(rng)).collect();
+        let commit_s = inner_product(&sl, &g_basis) + inner_product(&sr, &h_basis) + (s_blinding * points.b_i());
+
+        append_domain(transcript, range);
+        append_point(transcript, b"V", &commit_v);
+        append_point(transcript, b"A", &commit_a);
+        append_point(transcript, b"S", &commit_s);
+        let y = challenge_scalar(transcript, b"y");
+        let z = challenge_scalar(transcript, b"z");
+
+        let yn = powers_gen(y, range);
+        let n2 = n2_gen(range);
+        let l0: Vec<Scalar> = al.iter().map(|x| x - z).collect();
+        let r0: Vec<Scalar> = (0..range).map(|i| (yn[i] * (ar[i] + z)) + (z * z * n2[i])).collect();
+        let r1: Vec<Scalar> = (0..range).map(|i| yn[i] * sr[i]).collect();
+        let t1 = scalar_product(&l0, &r1) + scalar_product(&sl, &r0);
+        let t2 = scalar_product(&sl, &r1);
+
+        let t1_blinding = Scalar::random(rng);
+        let t2_blinding = Scalar::random(rng);
+        let commit_t1 = (t1 * points.g_i()) + (t1_blinding * points.b_i());
+        let commit_t2 = (t2 * points.g_i()) + (t2_blinding * points.b_i());
+        append_point(transcript, b"T_1", &commit_t1);
+        append_point(transcript, b"T_2", &commit_t2);
+        let x = challenge_scalar(transcript, b"x");
+
+        let lx = vector_add(&l0, &vec_scalar_mul(&sl, &x));
+        let rx = vector_add(&r0, &vec_scalar_mul(&r1, &x));
+        let t_x = scalar_product(&lx, &rx);
+        let t_x_blinding = (z * z * opening.gamma()) + (t1_blinding * x) + (t2_blinding * x * x);
+        let e_blinding = a_blinding + (s_blinding * x);
+        transcript.append_message(b"t_x", t_x.as_bytes());
+        transcript.append_message(b"t_x_blinding", t_x_blinding.as_bytes());
+        transcript.append_message(b"e_blinding", e_blinding.as_bytes());
+        let q = challenge_scalar(transcript, b"w") * points.g_i();
+
+        let y_inv_h = points_hadamard_multiply(&powers_gen(y.invert(), range), &h_basis);
+        let (l_vec, r_vec, [a, b]) = create_ipp(transcript, [g_basis, y_inv_h], q, [lx, rx]);
+
+        Ok((Self {
+            commit_a,
+            commit_s,
+            commit_t1,
+            commit_t2,
+            t_x,
+            t_x_blinding,
+            e_blinding,
+            l_vec,
+            r_vec,
+            a,
+            b,
+        }, commit_v))
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, range: usize) -> Result<(), ProofError> {
+        check_compat_range(points, range)?;
+        if self.l_vec.len() != self.r_vec.len() || 1 << self.l_vec.len() != range {
+            return Err(ProofError::VerificationError);
+        }
+        let identity = RistrettoPoint::identity();
+        if [self.commit_a, self.commit_s, self.commit_t1, self.commit_t2].contains(&identity) {
+            return Err(ProofError::VerificationError);
+        }
+        let g_basis = points.g_basis()[..range].to_vec();
+        let h_basis = points.h_basis()[..range].to_vec();
+
+        append_domain(transcript, range);
+        append_point(transcript, b"V", &commit_v);
+        append_point(transcript, b"A", &self.commit_a);
+        append_point(transcript, b"S", &self.commit_s);
+        let y = challenge_scalar(transcript, b"y");
+        let z = challenge_scalar(transcript, b"z");
+        append_point(transcript, b"T_1", &self.commit_t1);
+        append_point(transcript, b"T_2", &self.commit_t2);
+        let x = challenge_scalar(transcript, b"x");
+        transcript.append_message(b"t_x", self.t_x.as_bytes());
+        transcript.append_message(b"t_x_blinding", self.t_x_blinding.as_bytes());
+        transcript.append_message(b"e_blinding", self.e_blinding.as_bytes());
+        let q = challenge_scalar(transcript, b"w") * points.g_i();
+
+        let yn = powers_gen(y, range);
+        let n2 = n2_gen(range);
+        let z2 = z * z;
+        let delta = ((z - z2) * yn.iter().sum::<Scalar>()) - (z2 * z * n2.iter().sum::<Scalar>());
+        let eqn3lhs = (self.t_x * points.g_i()) + (self.t_x_blinding * points.b_i());
+        let eqn3rhs = (commit_v * z2) + (delta * points.g_i()) + (self.commit_t1 * x) + (self.commit_t2 * x * x);
+        if eqn3lhs != eqn3rhs {
+            return Err(ProofError::VerificationError);
+        }
+
+        let y_inv_h = points_hadamard_multiply(&powers_gen(y.invert(), range), &h_basis);
+        let h_scalars: Vec<Scalar> = (0..range).map(|i| (z * yn[i]) + (z2 * n2[i])).collect();
+        let commit_p = self.commit_a + (self.commit_s * x) + inner_product(&vec![-z; range], &g_basis)
+            + inner_product(&h_scalars, &y_inv_h) - (self.e_blinding * points.b_i()) + (self.t_x * q);
+
+        self.verify_ipp(transcript, commit_p, [g_basis, y_inv_h], q)
+    }
+
+    // A, S, T_1, T_2, t_x, t_x_blinding, e_blinding, L_0, R_0, .., L_k, R_k, a, b
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_points(&mut bytes, &[self.commit_a, self.commit_s, self.commit_t1, self.commit_t2]);
+        write_scalars(&mut bytes, &[self.t_x, self.t_x_blinding, self.e_blinding]);
+        for (left, right) in self.l_vec.iter().zip(self.r_vec.iter()) {
+            write_points(&mut bytes, &[*left, *right]);
+        }
+        write_scalars(&mut bytes, &[self.a, self.b]);
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8], range: usize) -> Result<Self, ProofError> {
+        if ![8, 16, 32, 64].contains(&range) {
+            return Err(ProofError::InvalidBitsize);
+        }
+        let mut reader = Reader::new(bytes);
+        let [commit_a, commit_s, commit_t1, commit_t2] = [reader.read_point()?, reader.read_point()?, reader.read_point()?, reader.read_point()?];
+        let [t_x, t_x_blinding, e_blinding] = [reader.read_scalar()?, reader.read_scalar()?, reader.read_scalar()?];
+        let rounds = range.trailing_zeros() as usize;
+        reader.expect_rounds(64, rounds)?;
+        let mut l_vec = vec![];
+        let mut r_vec = vec![];
+        for _ in 0..rounds {
+            l_vec.push(reader.read_point()?);
+            r_vec.push(reader.read_point()?);
+        }
+        let [a, b] = [reader.read_scalar()?, reader.read_scalar()?];
+        reader.finish()?;
+
+        Ok(Self {
+            commit_a,
+            commit_s,
+            commit_t1,
+            commit_t2,
+            t_x,
+            t_x_blinding,
+            e_blinding,
+            l_vec,
+            r_vec,
+            a,
+            b,
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str, range: usize) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?, range)
+    }
+
+    pub fn to_armored(&self, range: usize) -> String {
+        armor("CompatRangeProof", range, &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<(Self, usize), ProofError> {
+        let (range, bytes) = dearmor(text, "CompatRangeProof")?;
+
+        Ok((Self::from_bytes(&bytes, range)?, range))
+    }
+
+    fn verify_ipp(&self, transcript: &mut Transcript, mut commit_p: RistrettoPoint, [mut g_basis, mut h_basis]: [Vec<RistrettoPoint>; 2], q: RistrettoPoint) -> Result<(), ProofError> {
+        transcript.append_message(b"dom-sep", b"ipp v1");
+        transcript.append_u64(b"n", g_basis.len() as u64);
+
+        for (left, right) in self.l_vec.iter().zip(self.r_vec.iter()) {
+            append_point(transcript, b"L", left);
+            append_point(transcript, b"R", right);
+            let u = challenge_scalar(transcript, b"u");
+            let u_inv = u.invert();
+
+            commit_p = (left * u * u) + commit_p + (right * u_inv * u_inv);
+            g_basis = fold_halves(&g_basis, u_inv, u);
+            h_basis = fold_halves(&h_basis, u, u_inv);
+        }
+
+        if commit_p == (self.a * g_basis[0]) + (self.b * h_basis[0]) + (self.a * self.b * q) {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    pub fn commit_a(&self) -> RistrettoPoint {
+        self.commit_a
+    }
+
+    pub fn commit_s(&self) -> RistrettoPoint {
+        self.commit_s
+    }
+
+    pub fn commit_t1(&self) -> RistrettoPoint {
+        self.commit_t1
+    }
+
+    pub fn commit_t2(&self) -> RistrettoPoint {
+        self.commit_t2
+    }
+
+    pub fn t_x(&self) -> Scalar {
+        self.t_x
+    }
+
+    pub fn t_x_blinding(&self) -> Scalar {
+        self.t_x_blinding
+    }
+
+    pub fn e_blinding(&self) -> Scalar {
+        self.e_blinding
+    }
+
+    pub fn l_vec(&self) -> Vec<RistrettoPoint> {
+        self.l_vec.clone()
+    }
+
+    pub fn r_vec(&self) -> Vec<RistrettoPoint> {
+        self.r_vec.clone()
+    }
+
+    pub fn a(&self) -> Scalar {
+        self.a
+    }
+
+    pub fn b(&self) -> Scalar {
+        self.b
+    }
+}
+
+// Re-proves every recorded vector of the other implementation with the same seed and checks
+// that the bytes match and that the recorded proof verifies here.
+pub fn check_known_answers() -> Result<(), ProofError> {
+    let points = GlobalPoints::gen_dalek(64);
+    for line in KNOWN_ANSWERS.lines().filter(|x| !x.starts_with('#') && !x.trim().is_empty()) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [seed, value, blinding, range, commitment, proof] = fields[..] else {
+            return Err(ProofError::VerificationError);
+        };
+        let [seed, value, blinding, range] = [seed, value, blinding, range].map(|x| x.parse::<u64>().map_err(|_| ProofError::VerificationError));
+        let (seed, value, blinding, range) = (seed?, value?, blinding?, range? as usize);
+        let opening = Opening::new(value, Scalar::from(blinding));
+        let label = b"dalek interop kat";
+
+        let (ours, commit_v) = CompatRangeProof::prove_with_rng(&mut Transcript::new(label), &points, &opening, range, &mut ChaCha20Rng::seed_from_u64(seed))?;
+        if to_hex(commit_v.compress().as_bytes()) != commitment || to_hex(&ours.to_bytes()) != proof {
+            return Err(ProofError::VerificationError);
+        }
+        let recorded = CompatRangeProof::from_bytes(&from_hex(proof)?, range)?;
+        recorded.verify(&mut Transcript::new(label), &points, commit_v, range)?;
+    }
+    event!("dalek known-answer vectors match");
+
+    Ok(())
+}
+
+const KNOWN_ANSWERS: &str = include_str!("../vectors/dalek_rangeproof.txt");
+
+// The other implementation only accepts these widths for single-value proofs.
+fn check_compat_range(points: &GlobalPoints, range: usize) -> Result<(), ProofError> {
+    if ![8, 16, 32, 64].contains(&range) {
+        return Err(ProofError::InvalidBitsize);
+    }
+    if points.g_basis().len() < range {
+        return Err(ProofError::InvalidGeneratorsLength);
+    }
+
+    Ok(())
+}
+
+// Half-split folding: a' = a_lo * u + a_hi * u^-1, b' = b_lo * u^-1 + b_hi * u
+fn create_ipp(transcript: &mut Transcript, [mut g_basis, mut h_basis]: [Vec<RistrettoPoint>; 2], q: RistrettoPoint, [mut a, mut b]: [Vec<Scalar>; 2]) -> (Vec<RistrettoPoint>, Vec<RistrettoPoint>, [Scalar; 2]) {
+    transcript.append_message(b"dom-sep", b"ipp v1");
+    transcript.append_u64(b"n", a.len() as u64);
+    let mut l_vec = vec![];
+    let mut r_vec = vec![];
+
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a1, a2) = a.split_at(half);
+        let (b1, b2) = b.split_at(half);
+        let (g1, g2) = g_basis.split_at(half);
+        let (h1, h2) = h_basis.split_at(half);
+        let left = inner_product(a1, g2) + inner_product(b2, h1) + (scalar_product(a1, b2) * q);
+        let right = inner_product(a2, g1) + inner_product(b1, h2) + (scalar_product(a2, b1) * q);
+        append_point(transcript, b"L", &left);
+        append_point(transcript, b"R", &right);
+        let u = challenge_scalar(transcript, b"u");
+        let u_inv = u.invert();
+
+        a = a1.iter().zip(a2).map(|(x1, x2)| (x1 * u) + (x2 * u_inv)).collect();
+        b = b1.iter().zip(b2).map(|(x1, x2)| (x1 * u_inv) + (x2 * u)).collect();
+        g_basis = fold_halves(&g_basis, u_inv, u);
+        h_basis = fold_halves(&h_basis, u, u_inv);
+        l_vec.push(left);
+        r_vec.push(right);
+    }
+
+    (l_vec, r_vec, [a[0], b[0]])
+}
+
+fn fold_halves(basis: &[RistrettoPoint], first: Scalar, second: Scalar) -> Vec<RistrettoPoint> {
+    let (basis1, basis2) = basis.split_at(basis.len() / 2);
+    basis1.iter().zip(basis2).map(|(x1, x2)| (x1 * first) + (x2 * second)).collect()
+}
+
+fn append_domain(transcript: &mut Transcript, range: usize) {
+    transcript.append_message(b"dom-sep", b"rangeproof v1");
+    transcript.append_u64(b"n", range as u64);
+    transcript.append_u64(b"m", 1);
+}
+
+fn append_point(transcript: &mut Transcript, label: &'static [u8], point: &RistrettoPoint) {
+    transcript.append_message(label, point.compress().as_bytes());
+}
+
+fn challenge_scalar(transcript: &mut Transcript, label: &'static [u8]) -> Scalar {
+    let mut buf = [0u8; 64];
+    transcript.challenge_bytes(label, &mut buf);
+
+    Scalar::from_bytes_mod_order_wide(&buf)
+}
+
+fn scalar_product(a: &[Scalar], b: &[Scalar]) -> Scalar {
+    a.iter().zip(b).map(|(x1, x2)| x1 * x2).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bulletproofs::{BulletproofGens, PedersenGens};
+
+    const LABEL: &[u8] = b"dalek interop kat";
+
+    // seed, value, blinding, n, commitment and proof of every line in the vector file.
+    fn vectors() -> Vec<(u64, u64, u64, usize, String, String)> {
+        KNOWN_ANSWERS.lines().filter(|x| !x.starts_with('#') && !x.trim().is_empty()).map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            (fields[0].parse().unwrap(), fields[1].parse().unwrap(), fields[2].parse().unwrap(), fields[3].parse().unwrap(), fields[4].to_string(), fields[5].to_string())
+        }).collect()
+    }
+
+    #[test]
+    fn shipped_vectors_match() {
+        assert_eq!(check_known_answers(), Ok(()));
+    }
+
+    // The vectors are reproduced with the bulletproofs crate itself, as described in the file header.
+    #[test]
+    fn vectors_come_from_the_dalek_crate() {
+        let (bp_gens, pc_gens) = (BulletproofGens::new(64, 1), PedersenGens::default());
+        for (seed, value, blinding, range, commitment, proof) in vectors() {
+            let (theirs, commit_v) = bulletproofs::RangeProof::prove_single_with_rng(&bp_gens, &pc_gens, &mut Transcript::new(LABEL),
+                value, &Scalar::from(blinding), range, &mut ChaCha20Rng::seed_from_u64(seed)).unwrap();
+
+            assert_eq!(to_hex(commit_v.as_bytes()), commitment);
+            assert_eq!(to_hex(&theirs.to_bytes()), proof);
+        }
+    }
+
+    #[test]
+    fn rejects_modified_vector() {
+        let points = GlobalPoints::gen_dalek(64);
+        let (_, value, blinding, range, _, proof) = vectors().remove(0);
+        let commit_v = Opening::new(value, Scalar::from(blinding)).commit(&points);
+        let recorded = CompatRangeProof::from_hex(&proof, range).unwrap();
+        assert_eq!(recorded.verify(&mut Transcript::new(LABEL), &points, commit_v, range), Ok(()));
+
+        let mut modified = CompatRangeProof::from_hex(&proof, range).unwrap();
+        modified.t_x += Scalar::ONE;
+        assert!(modified.verify(&mut Transcript::new(LABEL), &points, commit_v, range).is_err());
+        assert!(recorded.verify(&mut Transcript::new(LABEL), &points, commit_v + points.g_i(), range).is_err());
+        assert!(recorded.verify(&mut Transcript::new(b"other"), &points, commit_v, range).is_err());
+    }
+
+    #[test]
+    fn dalek_verifies_our_proofs() {
+        let points = GlobalPoints::gen_dalek(64);
+        let (bp_gens, pc_gens) = (BulletproofGens::new(64, 1), PedersenGens::default());
+        let (ours, commit_v) = CompatRangeProof::prove(&mut Transcript::new(LABEL), &points, &Opening::random(40000), 16).unwrap();
+        let theirs = bulletproofs::RangeProof::from_bytes(&ours.to_bytes()).unwrap();
+        let commit_v = commit_v.compress();
+
+        assert!(theirs.verify_single(&bp_gens, &pc_gens, &mut Transcript::new(LABEL), &commit_v, 16).is_ok());
+        assert!(theirs.verify_single(&bp_gens, &pc_gens, &mut Transcript::new(b"other"), &commit_v, 16).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use std::ops::Range;
+
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::AggregatedRangeProof;
+use crate::errors::ProofError;
+use crate::generator::GlobalPoints;
+use crate::prover::Opening;
+use crate::serialization::Reader;
+use crate::text::{armor_plain, dearmor_plain, from_hex, to_hex};
+use crate::transcript::Transcript;
+
+// Proves min <= v < max by showing both V - min * g_i and (max - 1) * g_i - V commit to
+// values in [0, 2^n), where 2^n covers the width of the interval.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IntervalProof {
+    proof: AggregatedRangeProof,
+}
+
+impl IntervalProof {
+    pub fn prove(transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, interval: Range<u64>) -> Result<(Self, RistrettoPoint), ProofError> {
+        let range = interval_bits(&interval)?;
+        if !interval.contains(&opening.value()) {
+            return Err(ProofError::ValueOutOfRange);
+        }
+        let commit_v = opening.commit(points);
+        append_interval(transcript, commit_v, &interval);
+
+        let openings = [
+            Opening::new(opening.value() - interval.start, opening.gamma()),
+            Opening::new(interval.end - 1 - opening.value(), -opening.gamma()),
+        ];
+        let (proof, _) = AggregatedRangeProof::prove(transcript, points, &openings, &[range, range])?;
+
+        Ok((Self { proof }, commit_v))
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, interval: Range<u64>) -> Result<(), ProofError> {
+        let range = interval_bits(&interval)?;
+        append_interval(transcript, commit_v, &interval);
+
+        let commits = [
+            commit_v - (Scalar::from(interval.start) * points.g_i()),
+            (Scalar::from(interval.end - 1) * points.g_i()) - commit_v,
+        ];
+        self.proof.verify(transcript, points, &commits, &[range, range])
+    }
+
+    // The aggregated proof over the two bounds, whose encoding carries the shared width.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.proof.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = Reader::new(bytes);
+        let proof = IntervalProof::read(&mut reader)?;
+        reader.finish()?;
+
+        Ok(proof)
+    }
+
+    // Reads one proof off the front of `reader`; both bounds must have the same width.
+    pub(crate) fn read(reader: &mut Reader) -> Result<Self, ProofError> {
+        let proof = AggregatedRangeProof::read(reader)?;
+        if !matches!(proof.ranges(), [low, high] if low == high) {
+            return Err(ProofError::InvalidAggregation);
+        }
+
+        Ok(Self { proof })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?)
+    }
+
+    pub fn to_armored(&self) -> String {
+        armor_plain("IntervalProof", &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<Self, ProofError> {
+        Self::from_bytes(&dearmor_plain(text, "IntervalProof")?)
+    }
+
+    pub fn proof(&self) -> &AggregatedRangeProof {
+        &self.proof
+    }
+}
+
+pub fn interval_bits(interval: &Range<u64>) -> Result<usize, ProofError> {
+    if interval.is_empty() {
+        return Err(ProofError::InvalidInterval);
+    }
+    let width = interval.end - interval.start - 1;
+
+    Ok((64 - width.leading_zeros() as usize).max(1))
+}
+
+fn append_interval(transcript: &mut Transcript, commit_v: RistrettoPoint, interval: &Range<u64>) {
+    transcript.append_message(b"dom-sep", b"interval proof v1");
+    transcript.append_u64(b"min", interval.start);
+    transcript.append_u64(b"max", interval.end);
+    transcript.append_point(b"V", &commit_v);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::DecodeError;
+
+    #[test]
+    fn prove_verify_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commit_v) = IntervalProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(1500), 1000..2000).unwrap();
+
+        assert_eq!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, 1000..2000), Ok(()));
+    }
+
+    #[test]
+    fn rejects_other_interval() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commit_v) = IntervalProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(1500), 1000..2000).unwrap();
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, 1000..1500).is_err());
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v + points.g_i(), 1000..2000).is_err());
+    }
+
+    #[test]
+    fn rejects_value_outside_interval() {
+        let points = GlobalPoints::gen_global(32);
+
+        assert!(matches!(IntervalProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(2000), 1000..2000), Err(ProofError::ValueOutOfRange)));
+        assert_eq!(interval_bits(&(5..5)), Err(ProofError::InvalidInterval));
+        assert_eq!(interval_bits(&(0..257)), Ok(9));
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commit_v) = IntervalProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(1500), 1000..2000).unwrap();
+        let from_bytes = IntervalProof::from_bytes(&proof.to_bytes()).unwrap();
+        let from_hex = IntervalProof::from_hex(&proof.to_hex()).unwrap();
+        let from_armor = IntervalProof::from_armored(&proof.to_armored()).unwrap();
+
+        assert_eq!(from_bytes.to_bytes(), proof.to_bytes());
+        assert_eq!(from_hex.to_bytes(), proof.to_bytes());
+        assert_eq!(from_armor.verify(&mut Transcript::new(b"test"), &points, commit_v, 1000..2000), Ok(()));
+    }
+
+    #[test]
+    fn rejects_uneven_or_padded_bytes() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, _) = IntervalProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(1500), 1000..2000).unwrap();
+        let (aggregated, _) = AggregatedRangeProof::prove(&mut Transcript::new(b"test"), &points, &[Opening::random(1), Opening::random(1)], &[16, 8]).unwrap();
+
+        assert_eq!(IntervalProof::from_bytes(&aggregated.to_bytes()).err(), Some(ProofError::InvalidAggregation));
+        assert_eq!(IntervalProof::from_bytes(&[&proof.to_bytes()[..], &[0]].concat()).err(), Some(DecodeError::TrailingBytes.into()));
+        assert_eq!(IntervalProof::from_armored(&proof.to_hex()).err(), Some(DecodeError::InvalidArmor.into()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commit_v) = IntervalProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(1500), 1000..2000).unwrap();
+        let decoded: IntervalProof = serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+
+        assert_eq!(decoded.to_bytes(), proof.to_bytes());
+        assert_eq!(decoded.verify(&mut Transcript::new(b"test"), &points, commit_v, 1000..2000), Ok(()));
+    }
+}
@@ -0,0 +1,320 @@
+use std::fmt;
+use std::fs;
+
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde_json::{json, Value};
+
+use crate::bullerproof::InnerProductProof;
+use crate::errors::ProofError;
+use crate::generator::GlobalPoints;
+use crate::logging::event;
+use crate::prover::{ASVcommitment, Polycommitment, Salts, T1T2commitment};
+use crate::rangeproof::RangeProof;
+use crate::serialization::{write_points, write_scalars};
+use crate::text::to_hex;
+use crate::transcript::Transcript;
+
+const KAT_LABEL: &[u8] = b"known answer vector";
+
+// Every intermediate value of one RangeProof::prove run, in the order the prover computes them.
+// The generators and salts are drawn from ChaCha20 seeded with `seed`, so the whole run is
+// reproducible and any change to the protocol shows up as the first step that differs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KnownAnswer {
+    seed: u64,
+    value: u64,
+    range: usize,
+    steps: Vec<(String, Value)>,
+    proof: String,
+}
+
+#[derive(Debug)]
+pub enum KatError {
+    Proof(ProofError),
+    Io(std::io::Error),
+    InvalidVector(String),
+    Mismatch { step: String, expected: String, found: String },
+}
+
+impl KnownAnswer {
+    pub fn generate(seed: u64, value: u64, range: usize) -> Result<Self, ProofError> {
+        let mut steps = vec![];
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let points = GlobalPoints::from_rng(range, &mut rng);
+        let salt = Salts::from_rng(&mut rng);
+        let mut record = |name: &str, value: Value| steps.push((name.to_string(), value));
+
+        record("g_basis", points_json(points.g_basis()));
+        record("h_basis", points_json(points.h_basis()));
+        record("g_i", point_json(&points.g_i()));
+        record("b_i", point_json(&points.b_i()));
+        record("alpha", scalar_json(&salt.alpha()));
+        record("beta", scalar_json(&salt.beta()));
+        record("gamma", scalar_json(&salt.gamma()));
+        record("tau1", scalar_json(&salt.tau1()));
+        record("tau2", scalar_json(&salt.tau2()));
+        record("seed_s", json!(to_hex(&salt.seed_s())));
+
+        // Same steps as RangeProof::prove, with each value recorded as soon as it exists.
+        let asv = ASVcommitment::compute(value, range, &salt, &points);
+        let [al, ar, sl, sr] = asv.polynomial_const();
+        let [commit_a, commit_s, commit_v] = asv.to_verifier();
+        record("al", scalars_json(al));
+        record("ar", scalars_json(ar));
+        record("sl", scalars_json(sl));
+        record("sr", scalars_json(sr));
+        record("A", point_json(&commit_a));
+        record("S", point_json(&commit_s));
+        record("V", point_json(&commit_v));
+
+        let mut transcript = Transcript::new(KAT_LABEL);
+        transcript.append_message(b"dom-sep", b"rangeproof v1");
+        transcript.append_u64(b"n", range as u64);
+        transcript.append_point(b"V", &commit_v);
+        transcript.append_point(b"A", &commit_a);
+        transcript.append_point(b"S", &commit_s);
+        let y = transcript.challenge_scalar(b"y");
+        let z = transcript.challenge_scalar(b"z");
+        record("y", scalar_json(&y));
+        record("z", scalar_json(&z));
+
+        let t1t2 = T1T2commitment::init(&salt, &asv, y, z, range, &points);
+        let [commit_t1, commit_t2] = [t1t2.commit_t1(), t1t2.commit_t2()];
+        record("tx", scalars_json(&t1t2.access_tx()));
+        record("T1", point_json(&commit_t1));
+        record("T2", point_json(&commit_t2));
+        transcript.append_point(b"T1", &commit_t1);
+        transcript.append_point(b"T2", &commit_t2);
+        let u = transcript.challenge_scalar(b"u");
+        record("u", scalar_json(&u));
+
+        let poly = Polycommitment::compute(u, salt, &asv, &t1t2, y, z, range, &points);
+        record("lu", scalars_json(poly.lu()));
+        record("ru", scalars_json(poly.ru()));
+        record("tu", scalar_json(&poly.tu()));
+        record("pi_t", scalar_json(&poly.pi_t()));
+        record("pi_lr", scalar_json(&poly.pi_lr()));
+        record("C", point_json(&poly.commit_c()));
+        transcript.append_scalar(b"t_x", &poly.tu());
+        transcript.append_scalar(b"t_x_blinding", &poly.pi_t());
+        transcript.append_scalar(b"e_blinding", &poly.pi_lr());
+        let w = transcript.challenge_scalar(b"w");
+        record("w", scalar_json(&w));
+
+        // The folding challenges are re-derived from a copy of the transcript, the same way the verifier sees them.
+        let mut replay = transcript.clone();
+        let g_basis = points.g_basis()[..range].to_vec();
+        let ipp = InnerProductProof::create(&mut transcript, [g_basis, poly.y_inv_h().to_vec()], w * points.g_i(), [poly.lu().to_vec(), poly.ru().to_vec()]);
+        for (round, (left, right)) in ipp.l_vec().iter().zip(ipp.r_vec().iter()).enumerate() {
+            replay.append_point(b"L", left);
+            replay.append_point(b"R", right);
+            record(&format!("round {} L", round), point_json(left));
+            record(&format!("round {} R", round), point_json(right));
+            record(&format!("round {} u", round), scalar_json(&replay.challenge_scalar(b"u")));
+        }
+        record("a", scalar_json(&ipp.a()));
+        record("b", scalar_json(&ipp.b()));
+
+        // The recorded proof comes from RangeProof::prove itself, so a trace that drifts from it is caught here.
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let points = GlobalPoints::from_rng(range, &mut rng);
+        let (proof, commit) = RangeProof::prove(&mut Transcript::new(KAT_LABEL), &points, value, range, Salts::from_rng(&mut rng))?;
+        if commit != commit_v || proof.to_bytes() != trace_bytes([commit_a, commit_s, commit_t1, commit_t2], &poly, &ipp) {
+            return Err(ProofError::VerificationError);
+        }
+        proof.verify(&mut Transcript::new(KAT_LABEL), &points, commit, range)?;
+
+        Ok(Self {
+            seed,
+            value,
+            range,
+            steps,
+            proof: to_hex(&proof.to_bytes()),
+        })
+    }
+
+    // Re-derives every step from the recorded seed, value and bit width and reports the first one that differs.
+    pub fn check(&self) -> Result<(), KatError> {
+        let fresh = KnownAnswer::generate(self.seed, self.value, self.range).map_err(KatError::Proof)?;
+        let missing = ("<missing>".to_string(), Value::Null);
+        for i in 0..self.steps.len().max(fresh.steps.len()) {
+            let (name, expected) = self.steps.get(i).unwrap_or(&missing);
+            let (found_name, found) = fresh.steps.get(i).unwrap_or(&missing);
+            if name != found_name || expected != found {
+                return Err(KatError::Mismatch {
+                    step: if name == &missing.0 { found_name.clone() } else { name.clone() },
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                });
+            }
+        }
+        if self.proof != fresh.proof {
+            return Err(KatError::Mismatch {
+                step: "proof".to_string(),
+                expected: self.proof.clone(),
+                found: fresh.proof,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        let steps: Vec<Value> = self.steps.iter().map(|(name, value)| json!({ "name": name, "value": value })).collect();
+        let vector = json!({
+            "protocol": "rangeproof v1",
+            "label": String::from_utf8_lossy(KAT_LABEL),
+            "seed": self.seed,
+            "value": self.value,
+            "range": self.range,
+            "steps": steps,
+            "proof": self.proof,
+        });
+
+        serde_json::to_string_pretty(&vector).unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, KatError> {
+        let vector: Value = serde_json::from_str(text).map_err(|err| KatError::InvalidVector(err.to_string()))?;
+        let field = |name: &str| vector.get(name).ok_or_else(|| KatError::InvalidVector(format!("missing field {}", name)));
+        let number = |name: &str| field(name)?.as_u64().ok_or_else(|| KatError::InvalidVector(format!("{} is not an integer", name)));
+
+        let mut steps = vec![];
+        for step in field("steps")?.as_array().ok_or_else(|| KatError::InvalidVector("steps is not a list".to_string()))? {
+            let name = step.get("name").and_then(Value::as_str).ok_or_else(|| KatError::InvalidVector("step without a name".to_string()))?;
+            let value = step.get("value").ok_or_else(|| KatError::InvalidVector(format!("step {} without a value", name)))?;
+            steps.push((name.to_string(), value.clone()));
+        }
+
+        Ok(Self {
+            seed: number("seed")?,
+            value: number("value")?,
+            range: number("range")? as usize,
+            steps,
+            proof: field("proof")?.as_str().ok_or_else(|| KatError::InvalidVector("proof is not a string".to_string()))?.to_string(),
+        })
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), KatError> {
+        fs::write(path, self.to_json()).map_err(KatError::Io)
+    }
+
+    pub fn read(path: &str) -> Result<Self, KatError> {
+        KnownAnswer::from_json(&fs::read_to_string(path).map_err(KatError::Io)?)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn range(&self) -> usize {
+        self.range
+    }
+
+    pub fn steps(&self) -> Vec<(String, Value)> {
+        self.steps.clone()
+    }
+
+    pub fn proof(&self) -> String {
+        self.proof.clone()
+    }
+}
+
+// Checks the vectors shipped in vectors/ against the current prover.
+pub fn check_known_answers() -> Result<(), KatError> {
+    for text in KNOWN_ANSWERS {
+        KnownAnswer::from_json(text)?.check()?;
+    }
+    event!(vectors = KNOWN_ANSWERS.len(), "range proof known-answer vectors match");
+
+    Ok(())
+}
+
+const KNOWN_ANSWERS: [&str; 2] = [
+    include_str!("../vectors/rangeproof_v1_8.json"),
+    include_str!("../vectors/rangeproof_v1_16.json"),
+];
+
+impl fmt::Display for KatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KatError::Proof(err) => write!(f, "Proving the vector failed: {}", err),
+            KatError::Io(err) => write!(f, "Could not access the vector file: {}", err),
+            KatError::InvalidVector(reason) => write!(f, "Vector file is malformed: {}", reason),
+            KatError::Mismatch { step, expected, found } => write!(f, "First difference at {}: expected {}, found {}", step, expected, found),
+        }
+    }
+}
+
+impl std::error::Error for KatError {}
+
+// Same layout as RangeProof::to_bytes.
+fn trace_bytes(commits: [RistrettoPoint; 4], poly: &Polycommitment, ipp: &InnerProductProof) -> Vec<u8> {
+    let mut bytes = vec![];
+    write_points(&mut bytes, &commits);
+    write_scalars(&mut bytes, &[poly.tu(), poly.pi_t(), poly.pi_lr()]);
+    bytes.extend(ipp.to_bytes());
+
+    bytes
+}
+
+fn point_json(point: &RistrettoPoint) -> Value {
+    json!(to_hex(point.compress().as_bytes()))
+}
+
+fn points_json(points: &[RistrettoPoint]) -> Value {
+    Value::Array(points.iter().map(point_json).collect())
+}
+
+fn scalar_json(scalar: &Scalar) -> Value {
+    json!(to_hex(scalar.as_bytes()))
+}
+
+fn scalars_json(scalars: &[Scalar]) -> Value {
+    Value::Array(scalars.iter().map(scalar_json).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_vectors_match_the_prover() {
+        check_known_answers().unwrap();
+    }
+
+    #[test]
+    fn changed_step_is_reported_first() {
+        let mut vector = KnownAnswer::from_json(KNOWN_ANSWERS[0]).unwrap();
+        let index = vector.steps.iter().position(|(name, _)| name == "S").unwrap();
+        vector.steps[index].1 = point_json(&RistrettoPoint::default());
+
+        match vector.check() {
+            Err(KatError::Mismatch { step, .. }) => assert_eq!(step, "S"),
+            other => panic!("expected a mismatch at S, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn blinding_vectors_are_independent() {
+        let vector = KnownAnswer::from_json(KNOWN_ANSWERS[0]).unwrap();
+        let step = |name: &str| vector.steps.iter().find(|(x, _)| x == name).unwrap().1.clone();
+
+        assert_ne!(step("sl"), step("sr"));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let vector = KnownAnswer::from_json(KNOWN_ANSWERS[1]).unwrap();
+
+        assert_eq!(KnownAnswer::from_json(&vector.to_json()).unwrap(), vector);
+        assert!(matches!(KnownAnswer::from_json("{}"), Err(KatError::InvalidVector(_))));
+    }
+}
@@ -0,0 +1,26 @@
+pub mod prover;
+pub mod generator;
+pub mod operations;
+pub mod verifier;
+pub mod bullerproof;
+pub mod transcript;
+pub mod errors;
+pub mod r1cs;
+pub mod rangeproof;
+pub mod transaction;
+pub mod aggregation;
+pub mod interval;
+pub mod disjunction;
+pub mod encoders;
+mod serialization;
+mod logging;
+pub mod metrics;
+pub mod equality;
+pub mod comparison;
+pub mod bulletproofs_plus;
+pub mod reciprocal;
+pub mod interop;
+pub mod kat;
+pub mod inspect;
+pub mod text;
+pub mod fixed;
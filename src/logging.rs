@@ -0,0 +1,127 @@
+// Progress reporting for the prover and verifier. With the `tracing` feature every phase is a
+// span carrying the phase name and bit width, and reports its elapsed time when it ends; the
+// events go to whatever subscriber the application installs. Without the feature all of this
+// compiles to nothing, so the library never writes to stdout.
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+pub(crate) struct Phase {
+    // Only held so the span stays entered until the phase is dropped.
+    #[cfg(feature = "tracing")]
+    #[allow(dead_code)]
+    span: tracing::span::EnteredSpan,
+    #[cfg(feature = "tracing")]
+    start: Instant,
+}
+
+impl Phase {
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn enter(phase: &'static str, bits: usize) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!("phase", phase, bits).entered(),
+            #[cfg(feature = "tracing")]
+            start: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Phase {
+    fn drop(&mut self) {
+        tracing::debug!(elapsed_us = self.start.elapsed().as_micros() as u64, "phase finished");
+    }
+}
+
+// Debug event inside the current phase, same arguments as tracing::debug!.
+macro_rules! event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*)
+    };
+}
+
+pub(crate) use event;
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "tracing"))]
+    #[test]
+    fn phases_compile_to_nothing() {
+        assert_eq!(std::mem::size_of::<super::Phase>(), 0);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn prover_and_verifier_report_their_phases() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        use crate::generator::GlobalPoints;
+        use crate::prover::Salts;
+        use crate::rangeproof::RangeProof;
+        use crate::transcript::Transcript;
+
+        // Keeps the phase name of every span and counts the events.
+        #[derive(Default)]
+        struct Recorder {
+            phases: Arc<Mutex<Vec<String>>>,
+            events: Arc<Mutex<usize>>,
+        }
+
+        struct PhaseName(Option<String>);
+
+        impl Visit for PhaseName {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                if field.name() == "phase" {
+                    self.0 = Some(value.to_string());
+                }
+            }
+
+            fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut name = PhaseName(None);
+                span.record(&mut name);
+                let mut phases = self.phases.lock().unwrap();
+                phases.extend(name.0);
+
+                Id::from_u64(phases.len() as u64 + 1)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &Event<'_>) {
+                *self.events.lock().unwrap() += 1;
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let recorder = Recorder::default();
+        let (phases, events) = (recorder.phases.clone(), recorder.events.clone());
+        tracing::subscriber::with_default(recorder, || {
+            let points = GlobalPoints::gen_global(8);
+            let (proof, commit_v) = RangeProof::prove(&mut Transcript::new(b"test"), &points, 200, 8, Salts::init()).unwrap();
+            proof.verify(&mut Transcript::new(b"test"), &points, commit_v, 8).unwrap();
+        });
+
+        let phases = phases.lock().unwrap();
+        for phase in ["generators", "ASV commitments", "T1 T2 commitments", "polynomial commitment", "inner product proof"] {
+            assert!(phases.iter().any(|x| x == phase), "no {} span in {:?}", phase, phases);
+        }
+        assert!(*events.lock().unwrap() >= phases.len());
+    }
+}
@@ -1,24 +1,12 @@
-use bullerproof::prove_commitments_log;
-use curve25519_dalek::{scalar::Scalar, traits::Identity};
-use curve25519_dalek::ristretto::RistrettoPoint;
-use operations::{to_bin, vector_sub, scalarize};
-use generator::GlobalPoints;
-use prover::{ASVcommitment, BulletProof, Polycommitment, Salts, T1T2commitment};
-use rand::rngs::OsRng;
-use verifier::{BulletVerify, Generatives, LinearVerify};
-
-mod prover;
-mod generator;
-mod operations;
-mod verifier;
-mod bullerproof;
+use range_proof::bullerproof::prove_commitments_log;
+use range_proof::generator::GlobalPoints;
+use range_proof::prover::{ASVcommitment, BulletProof, Polycommitment, Salts, T1T2commitment};
+use range_proof::verifier::{BulletVerify, Generatives};
 
 fn main() {
     // Prover
     let prover_value: u64 = 63;
-    let al = scalarize(&mut to_bin(prover_value));
     let range = 8; // 2^n for Bulletproof Verification
-    let ar = vector_sub( &al, &vec![Scalar::from(1u32)]);
     let salt = Salts::init();
 
     // Global
@@ -45,7 +33,7 @@ fn main() {
     // Bulletproof Verification
 
     // Prover
-    let [left, right] = BulletProof::compute_diagonal([poly.lu(), poly.ru()], [&mut points.g_basis(), &mut poly.y_inv_h()], points.g_i());
+    let [left, right] = BulletProof::compute_diagonal([poly.lu(), poly.ru()], [points.g_basis(), poly.y_inv_h()], points.g_i());
 
     // Verifier
     let mut verifier = BulletVerify::init([left, right], asv.to_verifier(), [commit_t1, commit_t2], poly.bullet_verifier(), &points, gen.y_inv_h(), z, range);
@@ -54,12 +42,13 @@ fn main() {
     let mut prover = BulletProof::init(verifier.u_random(), [left, right], &poly, &points);
 
     // Prove commitments log
-    prove_commitments_log(range, &points, &mut prover, &mut verifier, &gen);
+    prove_commitments_log(range, &points, &mut prover, &mut verifier, &gen).expect("proof verifies");
+    println!("Verified successfully");
 
     // // Linear Verification
 
     // // Verifier
-    // let linear_verifier = LinearVerify::init_linear(range, &points, &poly, asv.to_verifier(), &gen, &t1t2);
+    // let linear_verifier = LinearVerify::init_linear(range, &points, &poly, asv.to_verifier(), &gen, &t1t2).unwrap();
 
-    // linear_verifier.verify();
+    // linear_verifier.verify().unwrap();
 }
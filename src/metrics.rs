@@ -0,0 +1,234 @@
+use std::cell::Cell;
+use std::ops::Sub;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+// Group operations done by the current thread. Only the primitives count: the helpers in
+// operations.rs, the folding functions and the generator multiplications in generator.rs, so
+// proofs built from them are measured without counting by hand. A scalar multiplication is
+// scalar * point, an inversion is a scalar field inversion.
+thread_local! {
+    static COUNTS: Cell<OperationCounts> = const { Cell::new(OperationCounts { scalar_muls: 0, point_adds: 0, inversions: 0 }) };
+}
+
+pub(crate) fn count_ops(scalar_muls: usize, point_adds: usize, inversions: usize) {
+    COUNTS.with(|counts| {
+        let mut current = counts.get();
+        current.scalar_muls += scalar_muls as u64;
+        current.point_adds += point_adds as u64;
+        current.inversions += inversions as u64;
+        counts.set(current);
+    });
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct OperationCounts {
+    scalar_muls: u64,
+    point_adds: u64,
+    inversions: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "lowercase"))]
+pub enum ProofPhase {
+    Commitment,
+    T1T2,
+    Polynomial,
+    Folding { round: usize },
+    Verification,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PhaseMetrics {
+    phase: ProofPhase,
+    elapsed: Duration,
+    counts: OperationCounts,
+}
+
+// Collects wall time and operation counts for each phase of a proof or verification.
+// Pass the same collector to several calls to accumulate their phases in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Metrics {
+    phases: Vec<PhaseMetrics>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn measure<T>(&mut self, phase: ProofPhase, f: impl FnOnce() -> T) -> T {
+        let before = COUNTS.with(|counts| counts.get());
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        let counts = COUNTS.with(|counts| counts.get()) - before;
+        self.phases.push(PhaseMetrics { phase, elapsed, counts });
+
+        result
+    }
+
+    pub fn phases(&self) -> Vec<PhaseMetrics> {
+        self.phases.clone()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.phases.iter().map(|x| x.elapsed).sum()
+    }
+
+    pub fn total(&self) -> OperationCounts {
+        self.phases.iter().fold(OperationCounts::default(), |total, x| OperationCounts {
+            scalar_muls: total.scalar_muls + x.counts.scalar_muls,
+            point_adds: total.point_adds + x.counts.point_adds,
+            inversions: total.inversions + x.counts.inversions,
+        })
+    }
+}
+
+impl ProofPhase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProofPhase::Commitment => "commitment",
+            ProofPhase::T1T2 => "t1t2",
+            ProofPhase::Polynomial => "polynomial",
+            ProofPhase::Folding { .. } => "folding",
+            ProofPhase::Verification => "verification",
+        }
+    }
+}
+
+impl PhaseMetrics {
+    pub fn phase(&self) -> ProofPhase {
+        self.phase
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn counts(&self) -> OperationCounts {
+        self.counts
+    }
+}
+
+impl OperationCounts {
+    pub fn scalar_muls(&self) -> u64 {
+        self.scalar_muls
+    }
+
+    pub fn point_adds(&self) -> u64 {
+        self.point_adds
+    }
+
+    pub fn inversions(&self) -> u64 {
+        self.inversions
+    }
+}
+
+impl Sub for OperationCounts {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            scalar_muls: self.scalar_muls - other.scalar_muls,
+            point_adds: self.point_adds - other.point_adds,
+            inversions: self.inversions - other.inversions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::{RistrettoPoint, Scalar};
+    use rand_core::OsRng;
+
+    use super::*;
+    use crate::bulletproofs_plus::BulletproofsPlusProof;
+    use crate::generator::GlobalPoints;
+    use crate::operations::{inner_product, invert, point_mul};
+    use crate::prover::{Opening, Salts};
+    use crate::rangeproof::RangeProof;
+    use crate::reciprocal::ReciprocalRangeProof;
+    use crate::transcript::Transcript;
+
+    fn folding_rounds(metrics: &Metrics) -> Vec<usize> {
+        metrics.phases().iter().filter_map(|x| match x.phase() {
+            ProofPhase::Folding { round } => Some(round),
+            _ => None,
+        }).collect()
+    }
+
+    fn counts_of(metrics: &Metrics, phase: ProofPhase) -> OperationCounts {
+        metrics.phases().iter().find(|x| x.phase() == phase).unwrap().counts()
+    }
+
+    #[test]
+    fn primitives_count_themselves() {
+        let points: Vec<RistrettoPoint> = (0..4).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+        let scalars: Vec<Scalar> = (0..4).map(|_| Scalar::random(&mut OsRng)).collect();
+        let mut metrics = Metrics::new();
+        metrics.measure(ProofPhase::Commitment, || inner_product(&scalars, &points));
+        metrics.measure(ProofPhase::Polynomial, || point_mul(&scalars[0], &points[0]));
+        metrics.measure(ProofPhase::Verification, || invert(&scalars[0]));
+
+        let counts: Vec<[u64; 3]> = metrics.phases().iter().map(|x| [x.counts().scalar_muls(), x.counts().point_adds(), x.counts().inversions()]).collect();
+        assert_eq!(counts, vec![[4, 4, 0], [1, 1, 0], [0, 0, 1]]);
+        assert_eq!(metrics.total().scalar_muls(), 5);
+    }
+
+    #[test]
+    fn range_proof_phases() {
+        let points = GlobalPoints::gen_global(16);
+        let mut metrics = Metrics::new();
+        let (proof, commit_v) = RangeProof::prove_with_metrics(&mut Transcript::new(b"test"), &points, 1000, 16, Salts::init(), &mut metrics).unwrap();
+        proof.verify_with_metrics(&mut Transcript::new(b"test"), &points, commit_v, 16, &mut metrics).unwrap();
+
+        let phases: Vec<ProofPhase> = metrics.phases().iter().map(|x| x.phase()).filter(|x| !matches!(x, ProofPhase::Folding { .. })).collect();
+        assert_eq!(phases, vec![ProofPhase::Commitment, ProofPhase::T1T2, ProofPhase::Polynomial, ProofPhase::Verification]);
+        assert_eq!(folding_rounds(&metrics), vec![0, 1, 2, 3]);
+        assert_eq!(counts_of(&metrics, ProofPhase::T1T2).scalar_muls(), 4);
+        assert!(metrics.phases().iter().all(|x| x.counts().scalar_muls() > 0));
+    }
+
+    #[test]
+    fn bulletproofs_plus_phases() {
+        let points = GlobalPoints::gen_global(16);
+        let mut metrics = Metrics::new();
+        let (proof, commit_v) = BulletproofsPlusProof::prove_with_metrics(&mut Transcript::new(b"test"), &points, &Opening::random(1000), 16, &mut metrics).unwrap();
+        proof.verify_with_metrics(&mut Transcript::new(b"test"), &points, commit_v, 16, &mut metrics).unwrap();
+
+        assert_eq!(folding_rounds(&metrics), vec![0, 1, 2, 3]);
+        assert!(counts_of(&metrics, ProofPhase::Commitment).scalar_muls() >= 32);
+        assert!(counts_of(&metrics, ProofPhase::Verification).inversions() > 0);
+    }
+
+    #[test]
+    fn reciprocal_phases() {
+        let points = GlobalPoints::gen_global(32);
+        let mut metrics = Metrics::new();
+        let (proof, commit_v) = ReciprocalRangeProof::prove_with_metrics(&mut Transcript::new(b"test"), &points, &Opening::random(1000), 16, &mut metrics).unwrap();
+        proof.verify_with_metrics(&mut Transcript::new(b"test"), &points, commit_v, 16, &mut metrics).unwrap();
+
+        assert_eq!(folding_rounds(&metrics), vec![0, 1, 2, 3, 4]);
+        assert_eq!(counts_of(&metrics, ProofPhase::Commitment).inversions(), 1);
+        assert!(metrics.phases().iter().all(|x| x.counts().scalar_muls() > 0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_phases() {
+        let mut metrics = Metrics::new();
+        metrics.measure(ProofPhase::T1T2, || invert(&Scalar::from(3u8)));
+        metrics.measure(ProofPhase::Folding { round: 2 }, || ());
+
+        let value = serde_json::to_value(&metrics).unwrap();
+        assert_eq!(value["phases"][0]["phase"], "t1t2");
+        assert_eq!(value["phases"][0]["counts"]["inversions"], 1);
+        assert_eq!(value["phases"][1]["phase"]["folding"]["round"], 2);
+    }
+}
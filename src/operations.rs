@@ -1,6 +1,8 @@
-use std::{ops::Mul, vec};
+use std::vec;
 
-use curve25519_dalek::{traits::Identity, RistrettoPoint, Scalar};
+use curve25519_dalek::{traits::{Identity, MultiscalarMul}, RistrettoPoint, Scalar};
+
+use crate::metrics::count_ops;
 
 pub fn to_bin(mut v: u64) -> Vec<i64> {
     let mut al: Vec<i64> = vec![];
@@ -13,18 +15,18 @@ pub fn to_bin(mut v: u64) -> Vec<i64> {
     al
 }
 
-pub fn to_dec(al: &Vec<i64>) -> u64 {
+pub fn to_dec(al: &[i64]) -> u64 {
     let v: i64 = al.iter().enumerate().map(|(i, &x)| 2i64.pow(i as u32) * x).sum();
 
     v as u64
 }
 
-pub fn scalarize(vector: &mut Vec<i64>) -> Vec<Scalar> {
+pub fn scalarize(vector: &mut [i64]) -> Vec<Scalar> {
     fn handle_val(value: i64) -> Scalar {
         if value >= 0 {
             Scalar::from(value as u64)
         } else {
-            Scalar::from(0u64) - Scalar::from(value.abs() as u64)
+            Scalar::from(0u64) - Scalar::from(value.unsigned_abs())
         }
     }
 
@@ -33,67 +35,183 @@ pub fn scalarize(vector: &mut Vec<i64>) -> Vec<Scalar> {
     scalar_vector
 }
 
-pub fn inv_vector(vector: &Vec<Scalar>) -> Vec<Scalar> {
-    vector.iter().map(|x| x.invert()).collect()
+// Montgomery's trick: one field inversion for the whole vector plus three multiplications per
+// element. Zero entries are skipped and stay zero, as Scalar::invert leaves them.
+pub fn inv_vector(vector: &[Scalar]) -> Vec<Scalar> {
+    let mut prefix = Vec::with_capacity(vector.len());
+    let mut product = Scalar::ONE;
+    for x in vector {
+        prefix.push(product);
+        if *x != Scalar::ZERO {
+            product *= x;
+        }
+    }
+
+    let mut inverse = invert(&product);
+    let mut inverted = vec![Scalar::ZERO; vector.len()];
+    for (i, x) in vector.iter().enumerate().rev() {
+        if *x != Scalar::ZERO {
+            inverted[i] = inverse * prefix[i];
+            inverse *= x;
+        }
+    }
+
+    inverted
 }
 
-pub fn inner_product(vector_1: &Vec<Scalar>, vector_2: &Vec<RistrettoPoint>) -> RistrettoPoint {
-    let mut value = vector_2[0];
+// scalar * point. This and the helpers below count their own operations for metrics.rs, so
+// callers never count by hand.
+pub fn point_mul(scalar: &Scalar, point: &RistrettoPoint) -> RistrettoPoint {
+    count_ops(1, 1, 0);
+    scalar * point
+}
 
-    for (a, b) in vector_1.iter().zip(vector_2.iter()) {
-        value = value + (a * b);
-    }
-    value = value - vector_2[0];
+pub fn point_sum(points: &[RistrettoPoint]) -> RistrettoPoint {
+    count_ops(0, points.len(), 0);
+    points.iter().sum()
+}
 
-    value
+pub fn invert(scalar: &Scalar) -> Scalar {
+    count_ops(0, 0, 1);
+    scalar.invert()
 }
 
-pub fn diagonal_ss_sum(vector1: &mut Vec<Scalar>, vector2: &mut Vec<Scalar> ) -> Scalar {
-    if vector1.len() % 2 !=0 {
-        vector1.insert(0, Scalar::from(0u8));
-        vector2.insert(0, Scalar::from(0u8));
-    }
-    let vec1:Vec<Scalar> = vector1.iter().enumerate().filter(|(i,_)| i % 2 == 0).map(|(_, &x)| x).collect();
-    let vec2:Vec<Scalar> = vector2.iter().enumerate().filter(|(i,_)| i % 2 != 0).map(|(_, &x)| x).collect();
-    vec1.iter().zip(vec2.iter()).map(|(x, y)| x * y).sum()
+// <vector_1, vector_2> as one constant-time multiscalar multiplication. Both vectors must have
+// the same length, callers cut the bases to the scalars, and a mismatch panics rather than
+// silently dropping terms; empty input gives the identity.
+pub fn inner_product(vector_1: &[Scalar], vector_2: &[RistrettoPoint]) -> RistrettoPoint {
+    assert_eq!(vector_1.len(), vector_2.len(), "inner product of vectors of different lengths");
+    count_ops(vector_1.len(), vector_1.len(), 0);
+
+    RistrettoPoint::multiscalar_mul(vector_1, vector_2)
 }
 
-pub fn diagonal_vs_sum(vector1: &mut Vec<RistrettoPoint>, vector2: &mut Vec<Scalar>) -> RistrettoPoint {
-    if vector1.len() % 2 !=0 {
-        vector1.insert(0, RistrettoPoint::identity());
-        vector2.insert(0, Scalar::from(0u8));
+// Sum of the inner products of several (scalars, points) pairs as a single multiscalar
+// multiplication, with the same length requirement per pair as inner_product.
+pub fn multiscalar_mul(terms: &[(&[Scalar], &[RistrettoPoint])]) -> RistrettoPoint {
+    for (x, y) in terms {
+        assert_eq!(x.len(), y.len(), "multiscalar multiplication of vectors of different lengths");
     }
-    let vec1:Vec<RistrettoPoint> = vector1.iter().enumerate().filter(|(i,_)| i % 2 == 0).map(|(_, &x)| x).collect();
-    let vec2:Vec<Scalar> = vector2.iter().enumerate().filter(|(i,_)| i % 2 != 0).map(|(_, &x)| x).collect();
-    vec1.iter().zip(vec2.iter()).map(|(x, y)| x * y).sum()
+    let scalars: Vec<Scalar> = terms.iter().flat_map(|(x, _)| x.iter()).copied().collect();
+    let points: Vec<RistrettoPoint> = terms.iter().flat_map(|(_, y)| y.iter()).copied().collect();
+    count_ops(scalars.len(), scalars.len(), 0);
+
+    RistrettoPoint::multiscalar_mul(&scalars, &points)
 }
 
-pub fn diagonal_sv_sum(vector1: &mut Vec<Scalar>, vector2: &mut Vec<RistrettoPoint> ) -> RistrettoPoint {
-    if vector1.len() % 2 !=0 {
-        vector1.insert(0, Scalar::from(0u8));
-        vector2.insert(0, RistrettoPoint::identity());
-    }
-    let vec1:Vec<Scalar> = vector1.iter().enumerate().filter(|(i,_)| i % 2 == 0).map(|(_, &x)| x).collect();
-    let vec2:Vec<RistrettoPoint> = vector2.iter().enumerate().filter(|(i,_)| i % 2 != 0).map(|(_, &x)| x).collect();
-    vec1.iter().zip(vec2.iter()).map(|(x, y)| x * y).sum()
+// Sum over x[2i] * y[2i + 1]. An odd-length input is read as if a zero were put in front of it,
+// matching the padding of the fold.
+pub fn diagonal_ss_sum(vector1: &[Scalar], vector2: &[Scalar]) -> Scalar {
+    evens(vector1, Scalar::ZERO).zip(odds(vector2, Scalar::ZERO)).map(|(x, y)| x * y).sum()
+}
+
+pub fn diagonal_vs_sum(vector1: &[RistrettoPoint], vector2: &[Scalar]) -> RistrettoPoint {
+    let len = vector1.len().min(vector2.len()).div_ceil(2);
+    count_ops(len, len, 0);
+
+    RistrettoPoint::multiscalar_mul(odds(vector2, Scalar::ZERO).take(len), evens(vector1, RistrettoPoint::identity()).take(len))
+}
+
+pub fn diagonal_sv_sum(vector1: &[Scalar], vector2: &[RistrettoPoint]) -> RistrettoPoint {
+    let len = vector1.len().min(vector2.len()).div_ceil(2);
+    count_ops(len, len, 0);
+
+    RistrettoPoint::multiscalar_mul(evens(vector1, Scalar::ZERO).take(len), odds(vector2, RistrettoPoint::identity()).take(len))
+}
+
+fn padded<T: Copy>(vector: &[T], zero: T) -> impl Iterator<Item = T> + '_ {
+    (!vector.len().is_multiple_of(2)).then_some(zero).into_iter().chain(vector.iter().copied())
+}
+
+fn evens<T: Copy>(vector: &[T], zero: T) -> impl Iterator<Item = T> + '_ {
+    padded(vector, zero).step_by(2)
 }
 
-pub fn vector_sub(vector1: &Vec<Scalar>, vector2: &Vec<Scalar>) -> Vec<Scalar> {
-    vector1.iter().zip(vector2.into_iter()).map(|(x, y)| x - y).collect()
+fn odds<T: Copy>(vector: &[T], zero: T) -> impl Iterator<Item = T> + '_ {
+    padded(vector, zero).skip(1).step_by(2)
 }
 
-pub fn vector_add(vector1: &Vec<Scalar>, vector2: &Vec<Scalar>) -> Vec<Scalar> {
-    vector1.iter().zip(vector2.into_iter()).map(|(x, y)| x + y).collect()
+pub fn vector_sub(vector1: &[Scalar], vector2: &[Scalar]) -> Vec<Scalar> {
+    vector1.iter().zip(vector2).map(|(x, y)| x - y).collect()
 }
 
-pub fn hadamard_multiply(vector1: &Vec<Scalar>, vector2: &Vec<Scalar>) -> Vec<Scalar> {
-    vector1.iter().zip(vector2.into_iter()).map(|(x, y)| x * y).collect()
+pub fn vector_add(vector1: &[Scalar], vector2: &[Scalar]) -> Vec<Scalar> {
+    vector1.iter().zip(vector2).map(|(x, y)| x + y).collect()
 }
 
-pub fn points_hadamard_multiply(vector1: &Vec<Scalar>, vector2: &Vec<RistrettoPoint>) -> Vec<RistrettoPoint> {
-    vector1.iter().zip(vector2.into_iter()).map(|(x, y)| x * y).collect()
+pub fn hadamard_multiply(vector1: &[Scalar], vector2: &[Scalar]) -> Vec<Scalar> {
+    vector1.iter().zip(vector2).map(|(x, y)| x * y).collect()
 }
 
-pub fn vec_scalar_mul(vector: &Vec<Scalar>, scalar: &Scalar) -> Vec<Scalar> {
+pub fn points_hadamard_multiply(vector1: &[Scalar], vector2: &[RistrettoPoint]) -> Vec<RistrettoPoint> {
+    count_ops(vector1.len().min(vector2.len()), 0, 0);
+    vector1.iter().zip(vector2).map(|(x, y)| x * y).collect()
+}
+
+pub fn vec_scalar_mul(vector: &[Scalar], scalar: &Scalar) -> Vec<Scalar> {
     vector.iter().map(|x| x * scalar).collect()
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use rand_core::OsRng;
+
+    use super::*;
+    use crate::metrics::{Metrics, ProofPhase};
+
+    fn random_terms(count: usize) -> (Vec<Scalar>, Vec<RistrettoPoint>) {
+        ((0..count).map(|_| Scalar::random(&mut OsRng)).collect(), (0..count).map(|_| RistrettoPoint::random(&mut OsRng)).collect())
+    }
+
+    #[test]
+    fn inner_product_matches_naive_sum() {
+        let (scalars, points) = random_terms(5);
+        let naive: RistrettoPoint = scalars.iter().zip(&points).map(|(x, y)| x * y).sum();
+
+        assert_eq!(inner_product(&scalars, &points), naive);
+        assert_eq!(inner_product(&[], &[]), RistrettoPoint::identity());
+    }
+
+    #[test]
+    fn multiscalar_mul_sums_its_terms() {
+        let (scalars1, points1) = random_terms(4);
+        let (scalars2, points2) = random_terms(3);
+
+        assert_eq!(multiscalar_mul(&[(&scalars1, &points1), (&scalars2, &points2)]), inner_product(&scalars1, &points1) + inner_product(&scalars2, &points2));
+        assert_eq!(multiscalar_mul(&[(&scalars1, &points1), (&[], &[])]), inner_product(&scalars1, &points1));
+        assert_eq!(multiscalar_mul(&[]), RistrettoPoint::identity());
+    }
+
+    #[test]
+    fn batch_inversion_matches_single_inversions() {
+        let (scalars, _) = random_terms(6);
+        let mut metrics = Metrics::new();
+        let inverted = metrics.measure(ProofPhase::Verification, || inv_vector(&scalars));
+
+        assert_eq!(inverted, scalars.iter().map(|x| x.invert()).collect::<Vec<Scalar>>());
+        assert_eq!(metrics.total().inversions(), 1);
+        assert_eq!(inv_vector(&[]), vec![]);
+    }
+
+    #[test]
+    fn batch_inversion_skips_zeros() {
+        let scalars = [Scalar::from(3u8), Scalar::ZERO, Scalar::from(5u8), Scalar::ZERO];
+        let inverted = inv_vector(&scalars);
+
+        assert_eq!(inverted, vec![Scalar::from(3u8).invert(), Scalar::ZERO, Scalar::from(5u8).invert(), Scalar::ZERO]);
+        assert_eq!(inv_vector(&[Scalar::ZERO; 3]), vec![Scalar::ZERO; 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "inner product of vectors of different lengths")]
+    fn inner_product_rejects_mismatched_lengths() {
+        let (scalars, points) = random_terms(4);
+        inner_product(&scalars, &points[..3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "multiscalar multiplication of vectors of different lengths")]
+    fn multiscalar_mul_rejects_mismatched_lengths() {
+        let (scalars, points) = random_terms(4);
+        multiscalar_mul(&[(&scalars[..2], &points[..2]), (&scalars, &points[..3])]);
+    }
+}
@@ -1,10 +1,13 @@
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use rand::rngs::OsRng;
-use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{CryptoRng, RngCore};
 use crate::bullerproof::{fold_scalar, fold_vector};
-use crate::operations::{points_hadamard_multiply, inv_vector, diagonal_ss_sum, diagonal_sv_sum, diagonal_vs_sum, hadamard_multiply, inner_product, scalarize, to_bin, vec_scalar_mul, vector_add, vector_sub};
-use crate::generator::{gen_scalars, n2_gen, GlobalPoints};
+use crate::operations::{points_hadamard_multiply, diagonal_ss_sum, diagonal_sv_sum, diagonal_vs_sum, hadamard_multiply, invert, multiscalar_mul, point_mul, scalarize, to_bin, vec_scalar_mul, vector_add, vector_sub};
+use crate::generator::{n2_gen, powers_gen, GlobalPoints};
+use crate::logging::{event, Phase};
 
 pub struct ASVcommitment {
     commit_a: RistrettoPoint,
@@ -22,6 +25,13 @@ pub struct Salts {
     salt_gamma: Scalar,
     salt_tau1: Scalar,
     salt_tau2: Scalar,
+    seed_s: [u8; 32],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Opening {
+    value: u64,
+    gamma: Scalar,
 }
 
 pub struct Polycommitment {
@@ -50,15 +60,22 @@ pub struct BulletProof {
     a: Vec<Scalar>,
     b: Vec<Scalar>,
     u_verifier: Scalar,
+    u_verifier_inv: Scalar,
 }
 
 impl BulletProof {
     pub fn init(u_random: Scalar, [left, right]: [RistrettoPoint; 2], poly: &Polycommitment, points: &GlobalPoints) -> Self {
-        let commit_p = (left * u_random * u_random) + (right * u_random.invert() * u_random.invert()) + poly.commit_c() + (poly.tu() * points.g_i());
-        let g_basis_fold = fold_vector(&mut points.g_basis().clone(), u_random.invert());
-        let h_basis_fold = fold_vector(&mut poly.y_inv_h(), u_random);
-        let a = fold_scalar(&mut poly.lu(), u_random);
-        let b = fold_scalar(&mut poly.ru(), u_random.invert());
+        let u_inv = u_random.invert();
+        let commit_p = (left * u_random * u_random) + (right * u_inv * u_inv) + poly.commit_c() + (poly.tu() * points.g_i());
+        let mut g_basis_fold = points.g_basis()[..poly.lu().len()].to_vec();
+        let mut h_basis_fold = poly.y_inv_h().to_vec();
+        let mut a = poly.lu().to_vec();
+        let mut b = poly.ru().to_vec();
+        fold_vector(&mut g_basis_fold, u_inv, u_random);
+        fold_vector(&mut h_basis_fold, u_random, u_inv);
+        fold_scalar(&mut a, u_random, u_inv);
+        fold_scalar(&mut b, u_inv, u_random);
+
         Self {
             left,
             right,
@@ -69,79 +86,81 @@ impl BulletProof {
             a,
             b,
             u_verifier: u_random,
+            u_verifier_inv: u_inv,
         }
     }
 
-    pub fn compute_diagonal([mut left, mut right]: [Vec<Scalar>; 2], [mut g_basis, mut h_basis]: [&mut Vec<RistrettoPoint>; 2], g_i: RistrettoPoint) -> [RistrettoPoint; 2] {
-        let new_left = (diagonal_ss_sum(&mut left, &mut right) * g_i) +
-            (diagonal_sv_sum(&mut left, &mut g_basis)) + (diagonal_vs_sum(&mut h_basis, &mut right));
-        let new_right = (diagonal_ss_sum(&mut right, &mut left) * g_i) +
-            (diagonal_vs_sum(&mut g_basis, &mut left)) + (diagonal_sv_sum(&mut right, &mut h_basis));
+    pub fn compute_diagonal([left, right]: [&[Scalar]; 2], [g_basis, h_basis]: [&[RistrettoPoint]; 2], g_i: RistrettoPoint) -> [RistrettoPoint; 2] {
+        let new_left = point_mul(&diagonal_ss_sum(left, right), &g_i) +
+            (diagonal_sv_sum(left, g_basis)) + (diagonal_vs_sum(h_basis, right));
+        let new_right = point_mul(&diagonal_ss_sum(right, left), &g_i) +
+            (diagonal_vs_sum(g_basis, left)) + (diagonal_sv_sum(right, h_basis));
         
         [new_left, new_right]
     }
 
-    pub fn a_fold(&mut self) -> () {
-        self.a = fold_scalar(&mut self.a(), self.u_verifier);
+    pub fn a_fold(&mut self) {
+        fold_scalar(&mut self.a, self.u_verifier, self.u_verifier_inv);
     }
 
-    pub fn b_fold(&mut self) -> () {
-        self.b = fold_scalar(&mut self.b(), self.u_verifier.invert());
+    pub fn b_fold(&mut self) {
+        fold_scalar(&mut self.b, self.u_verifier_inv, self.u_verifier);
     }
 
-    pub fn compute(&mut self, u_random: Scalar) -> () {
+    pub fn compute(&mut self, u_random: Scalar) {
         self.u_verifier = u_random;
-        self.commit_p = (self.left * self.u_verifier * self.u_verifier) + (self.right * self.u_verifier.invert() * self.u_verifier.invert()) + self.commit_p;
-        self.g_basis_fold = fold_vector(&mut self.g_basis_fold.clone(), self.u_verifier.invert());
-        self.h_basis_fold = fold_vector(&mut self.h_basis_fold.clone(), self.u_verifier);
+        self.u_verifier_inv = u_random.invert();
+        self.commit_p = (self.left * self.u_verifier * self.u_verifier) + (self.right * self.u_verifier_inv * self.u_verifier_inv) + self.commit_p;
+        fold_vector(&mut self.g_basis_fold, self.u_verifier_inv, self.u_verifier);
+        fold_vector(&mut self.h_basis_fold, self.u_verifier, self.u_verifier_inv);
         self.a_fold();
         self.b_fold();
     }
 
-    pub fn a(&self) -> Vec<Scalar> {
-        self.a.clone()
+    pub fn a(&self) -> &[Scalar] {
+        &self.a
     }
 
-    pub fn b(&self) -> Vec<Scalar> {
-        self.b.clone()
+    pub fn b(&self) -> &[Scalar] {
+        &self.b
     }
 
-    pub fn update_diagonals(&mut self, [left, right]: [RistrettoPoint; 2]) -> () {
+    pub fn update_diagonals(&mut self, [left, right]: [RistrettoPoint; 2]) {
         self.left = left;
         self.right = right;
     }
 
-    pub fn h_basis(&self) -> Vec<RistrettoPoint> {
-        self.h_basis_fold.clone()
+    pub fn h_basis(&self) -> &[RistrettoPoint] {
+        &self.h_basis_fold
     }
 
-    pub fn g_basis(&self) -> Vec<RistrettoPoint> {
-        self.g_basis_fold.clone()
+    pub fn g_basis(&self) -> &[RistrettoPoint] {
+        &self.g_basis_fold
     }
 
     pub fn commit_p(&self) -> RistrettoPoint {
         self.commit_p
     }
+
+    pub fn g_i(&self) -> RistrettoPoint {
+        self.g_i
+    }
 }
 
 impl T1T2commitment {
-    pub fn init(salt: &Salts, asv: &ASVcommitment, y: u64, z: u64, count: usize, points: &GlobalPoints) -> Self {
-        let yn = gen_scalars(count, y);
+    pub fn init(salt: &Salts, asv: &ASVcommitment, y: Scalar, z: Scalar, count: usize, points: &GlobalPoints) -> Self {
+        let _phase = Phase::enter("T1 T2 commitments", count);
+        let yn = powers_gen(y, count);
         let n2 = n2_gen(count);
-        let z2 = Scalar::from(z) * Scalar::from(z);
+        let z2 = z * z;
         let [al, ar, sl, sr] = asv.polynomial_const();
-        
-        let t0: Scalar= hadamard_multiply(&vector_sub(&al, &vec![Scalar::from(z); count]),
-            &vector_add(&vector_add(&hadamard_multiply(&yn, &ar), &vec_scalar_mul(&yn, &Scalar::from(z))), 
-            &vec_scalar_mul(&n2, &z2))).iter().sum();
-        let t1: Scalar = hadamard_multiply(&vector_sub(&al, &vec![Scalar::from(z); count]), &hadamard_multiply(&yn, &sr)).iter().sum::<Scalar>()
-            + hadamard_multiply(&vector_add(&hadamard_multiply(&yn, &vector_add(&ar, &vec![Scalar::from(z); count])),
-            &vec_scalar_mul(&n2, &z2)), &sl).iter().sum::<Scalar>();
-        let t2: Scalar = hadamard_multiply(&sl, &hadamard_multiply(&yn, &sr)).iter().sum();
-
-        let commit_t1 = (t1 * points.g_i()) + (salt.tau1() * points.b_i());
-        let commit_t2 = (t2 * points.g_i()) + (salt.tau2() * points.b_i());
-        println!("Generated T1, T2 commitments");
+
+        let l0 = vector_sub(al, &vec![z; count]);
+        let r0 = vector_add(&hadamard_multiply(&yn, &vector_add(ar, &vec![z; count])), &vec_scalar_mul(&n2, &z2));
+        let r1 = hadamard_multiply(&yn, sr);
+        let t0: Scalar = hadamard_multiply(&l0, &r0).iter().sum();
+        let [t1, t2] = t_coefficients([&l0[..], &r0[..]], [sl, &r1[..]]);
+        let [commit_t1, commit_t2] = commit_t(points, [t1, t2], salt);
 
         Self {
             commit_t1,
@@ -164,26 +183,27 @@ impl T1T2commitment {
 }
 
 impl Polycommitment {
-    pub fn compute(u: Scalar, salt: Salts, asv: &ASVcommitment, tx: &T1T2commitment, y: u64, z: u64, count: usize, points: &GlobalPoints) -> Self {
-        let yn = gen_scalars(count, y);
-        let y_inv_h = points_hadamard_multiply(&inv_vector(&yn), &points.h_basis());
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute(u: Scalar, salt: Salts, asv: &ASVcommitment, tx: &T1T2commitment, y: Scalar, z: Scalar, count: usize, points: &GlobalPoints) -> Self {
+        let _phase = Phase::enter("polynomial commitment", count);
+        let yn = powers_gen(y, count);
+        let y_inv_h = points_hadamard_multiply(&powers_gen(invert(&y), count), &points.h_basis()[..count]);
         let n2 = n2_gen(count);
-        let z2 = Scalar::from(z) * Scalar::from(z);
+        let z2 = z * z;
         let [al, ar, sl, sr] = asv.polynomial_const();
-        let l = vector_add(&vector_sub(&al, &vec![Scalar::from(z); count]), &vec_scalar_mul(&sl, &u));
-        let r = vector_add(&vector_add(&hadamard_multiply(&yn, &vector_add(&ar, &vec![Scalar::from(z); count])),
-            &vec_scalar_mul(&n2, &z2)), &hadamard_multiply(&yn, &vec_scalar_mul(&sr, &u)));
-        println!("Computed lu, ru");
+        let l = vector_add(&vector_sub(al, &vec![z; count]), &vec_scalar_mul(sl, &u));
+        let r = vector_add(&vector_add(&hadamard_multiply(&yn, &vector_add(ar, &vec![z; count])),
+            &vec_scalar_mul(&n2, &z2)), &hadamard_multiply(&yn, &vec_scalar_mul(sr, &u)));
+        event!("computed lu, ru");
 
         let [t0, t1, t2] = tx.access_tx();
         let t = t0 + (t1 * u) + (t2 * u * u);
-        println!("Computed tu");
+        event!("computed tu");
 
-        let pi_lr = salt.alpha() + (salt.beta() * u);
-        let pi_t = (z2 * salt.gamma()) + (salt.tau1() * u) + (salt.tau2() * u * u);
-        println!("Computed all polynomial terms");
+        let [pi_t, pi_lr] = blinding_openings(&salt, z, u);
+        event!("computed all polynomial terms");
 
-        let commit_c = inner_product(&l, &points.g_basis()) + inner_product(&r, &y_inv_h);
+        let commit_c = multiscalar_mul(&[(&l, &points.g_basis()[..count]), (&r, &y_inv_h)]);
         assert_eq!(hadamard_multiply(&l, &r).iter().sum::<Scalar>(), t, "Prover system mess up!!");
 
         Self {
@@ -201,12 +221,12 @@ impl Polycommitment {
         (self.commit_c, [self.t, self.pi_lr, self.pi_t])
     }
 
-    pub fn lu(&self) -> Vec<Scalar> {
-        self.l.clone()
+    pub fn lu(&self) -> &[Scalar] {
+        &self.l
     }
 
-    pub fn ru(&self) -> Vec<Scalar> {
-        self.r.clone()
+    pub fn ru(&self) -> &[Scalar] {
+        &self.r
     }
 
     pub fn tu(&self) -> Scalar {
@@ -225,19 +245,25 @@ impl Polycommitment {
         self.commit_c
     }
 
-    pub fn y_inv_h(&self) -> Vec<RistrettoPoint> {
-        self.y_inv_h.clone()
+    pub fn y_inv_h(&self) -> &[RistrettoPoint] {
+        &self.y_inv_h
     }
 }
 
 impl Salts {
     pub fn init() -> Self {
-        let mut rng = OsRng;
-        let alpha = Scalar::random(&mut rng);
-        let beta = Scalar::random(&mut rng);
-        let gamma = Scalar::random(&mut rng);
-        let tau1 = Scalar::random(&mut rng);
-        let tau2 = Scalar::random(&mut rng);
+        Salts::from_rng(&mut OsRng)
+    }
+
+    // All prover randomness comes from here, so a seeded rng gives a reproducible proof.
+    pub fn from_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let alpha = Scalar::random(rng);
+        let beta = Scalar::random(rng);
+        let gamma = Scalar::random(rng);
+        let tau1 = Scalar::random(rng);
+        let tau2 = Scalar::random(rng);
+        let mut seed_s = [0u8; 32];
+        rng.fill_bytes(&mut seed_s);
 
         Self {
             salt_alpha: alpha,
@@ -245,9 +271,17 @@ impl Salts {
             salt_gamma: gamma,
             salt_tau1: tau1,
             salt_tau2: tau2,
+            seed_s,
         }
     }
 
+    pub fn with_gamma(gamma: Scalar) -> Self {
+        let mut salt = Salts::init();
+        salt.salt_gamma = gamma;
+
+        salt
+    }
+
     pub fn alpha(&self) -> Scalar {
         self.salt_alpha
     }
@@ -263,44 +297,67 @@ impl Salts {
     pub fn tau2(&self) -> Scalar {
         self.salt_tau2
     }
+    pub fn seed_s(&self) -> [u8; 32] {
+        self.seed_s
+    }
+
+    // sl and sr for S, drawn one after the other from ChaCha20 keyed with the 256-bit seed_s,
+    // so the two vectors are independent but the proof stays reproducible from the salts.
+    pub fn blinding_vectors(&self, count: usize) -> [Vec<Scalar>; 2] {
+        let mut rng = ChaCha20Rng::from_seed(self.seed_s);
+        [(); 2].map(|_| (0..count).map(|_| Scalar::random(&mut rng)).collect())
+    }
 }
 
-impl ASVcommitment {
-    pub fn compute(v: u64, range: usize, salt: &Salts, points: &GlobalPoints) -> Self {
-        let salt_alpha = salt.alpha();
-        let salt_beta = salt.beta();
-        let salt_gamma = salt.gamma();
+impl Opening {
+    pub fn new(value: u64, gamma: Scalar) -> Self {
+        Self {
+            value,
+            gamma,
+        }
+    }
 
-        let rng: u64 = rand::thread_rng().gen();
-        let [mut al, mut ar] = ASVcommitment::compute_al_ar(v, range);
-        let sl = gen_scalars(range, rng);
-        let sr = gen_scalars(range, rng);
+    pub fn random(value: u64) -> Self {
+        Opening::new(value, Scalar::random(&mut OsRng))
+    }
 
-        println!("ASV commitment pre-requirements completed.");
+    pub fn commit(&self, points: &GlobalPoints) -> RistrettoPoint {
+        points.commit(&Scalar::from(self.value), &self.gamma)
+    }
 
-        let commit_a_val = inner_product(&scalarize(&mut al), &mut points.g_basis())
-            + inner_product(&scalarize(&mut ar), &mut points.h_basis()) + (salt_alpha * points.b_i());
+    pub fn value(&self) -> u64 {
+        self.value
+    }
 
-        let commit_s_val = inner_product(&sl, &mut points.g_basis())
-            + inner_product(&sr, &mut points.h_basis()) + (salt_beta * points.b_i());
-        
-        let commit_v_val = (Scalar::from(v) * points.g_i()) + (salt_gamma * points.b_i());
+    pub fn gamma(&self) -> Scalar {
+        self.gamma
+    }
+}
 
-        println!("ASV commitments ready!");
+impl ASVcommitment {
+    pub fn compute(v: u64, range: usize, salt: &Salts, points: &GlobalPoints) -> Self {
+        let _phase = Phase::enter("ASV commitments", range);
+        let [mut al, mut ar] = ASVcommitment::compute_al_ar(v, range);
+        let [al, ar] = [scalarize(&mut al), scalarize(&mut ar)];
+        let [sl, sr] = salt.blinding_vectors(range);
+
+        event!("ASV commitment pre-requirements completed");
+
+        let [commit_a, commit_s, commit_v] = commit_asv(points, v, [&al, &ar], [&sl, &sr], salt);
 
         Self {
-            commit_a: commit_a_val,
-            commit_s: commit_s_val,
-            commit_v: commit_v_val,
-            al: scalarize(&mut al),
-            ar: scalarize(&mut ar),
+            commit_a,
+            commit_s,
+            commit_v,
+            al,
+            ar,
             sl,
             sr,
         }
     }
 
-    fn compute_al_ar(v: u64, range: usize) -> [Vec<i64>; 2] {
-        let mut al= to_bin(v.clone());
+    pub(crate) fn compute_al_ar(v: u64, range: usize) -> [Vec<i64>; 2] {
+        let mut al= to_bin(v);
         while al.len() < range {
             al.push(0)
         }
@@ -310,12 +367,75 @@ impl ASVcommitment {
         [al, ar]
     }
 
-    pub fn polynomial_const(&self) -> [Vec<Scalar>; 4] {
-        [self.al.clone(), self.ar.clone(), self.sl.clone(), self.sr.clone()]
+    pub fn polynomial_const(&self) -> [&[Scalar]; 4] {
+        [&self.al, &self.ar, &self.sl, &self.sr]
     }
 
     pub fn to_verifier(&self) -> [RistrettoPoint; 3] {
-        println!("ASV commitments sent to verifier.");
         [self.commit_a, self.commit_s, self.commit_v]
     }
-}
\ No newline at end of file
+}
+// The commitment steps of the single-value range proof, shared by RangeProof::prove and
+// fixed::Prover. The vectors are Vecs in the first and [Scalar; BITS] arrays in the second.
+
+// A, S and V from the bit vectors al, ar and the blinding vectors sl, sr.
+pub(crate) fn commit_asv<V: AsRef<[Scalar]>>(points: &GlobalPoints, v: u64, [al, ar]: [V; 2], [sl, sr]: [V; 2], salt: &Salts) -> [RistrettoPoint; 3] {
+    [
+        points.vector_commit([al.as_ref(), ar.as_ref()], &salt.alpha()),
+        points.vector_commit([sl.as_ref(), sr.as_ref()], &salt.beta()),
+        points.commit(&Scalar::from(v), &salt.gamma()),
+    ]
+}
+
+// t1 and t2 of t(X) = <l0 + sl * X, r0 + r1 * X>: the cross and square terms.
+pub(crate) fn t_coefficients<V: AsRef<[Scalar]>>([l0, r0]: [V; 2], [sl, r1]: [V; 2]) -> [Scalar; 2] {
+    let dot = |x: &V, y: &V| x.as_ref().iter().zip(y.as_ref()).map(|(a, b)| a * b).sum::<Scalar>();
+
+    [dot(&l0, &r1) + dot(&sl, &r0), dot(&sl, &r1)]
+}
+
+pub(crate) fn commit_t(points: &GlobalPoints, [t1, t2]: [Scalar; 2], salt: &Salts) -> [RistrettoPoint; 2] {
+    [points.commit(&t1, &salt.tau1()), points.commit(&t2, &salt.tau2())]
+}
+
+// pi_t and pi_lr, the openings of the blinding factors at u.
+pub(crate) fn blinding_openings(salt: &Salts, z: Scalar, u: Scalar) -> [Scalar; 2] {
+    [(z * z * salt.gamma()) + (salt.tau1() * u) + (salt.tau2() * u * u), salt.alpha() + (salt.beta() * u)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::vector_sub;
+
+    #[test]
+    fn polynomial_vectors_borrow_the_commitment() {
+        let points = GlobalPoints::gen_global(8);
+        let asv = ASVcommitment::compute(0b1011_0001, 8, &Salts::init(), &points);
+        let [al, ar, sl, sr] = asv.polynomial_const();
+
+        assert_eq!(al, scalarize(&mut [1, 0, 0, 0, 1, 1, 0, 1]));
+        assert_eq!(ar, vector_sub(al, &[Scalar::ONE; 8]));
+        assert_eq!([sl.len(), sr.len()], [8, 8]);
+        assert!(std::ptr::eq(al, asv.polynomial_const()[0]));
+    }
+
+    #[test]
+    fn polynomial_evaluates_to_t() {
+        let points = GlobalPoints::gen_global(8);
+        let salt = Salts::init();
+        let asv = ASVcommitment::compute(200, 8, &salt, &points);
+        let [y, z, u] = [Scalar::from(3u8), Scalar::from(5u8), Scalar::from(7u8)];
+        let t1t2 = T1T2commitment::init(&salt, &asv, y, z, 8, &points);
+        let poly = Polycommitment::compute(u, salt, &asv, &t1t2, y, z, 8, &points);
+
+        assert_eq!(hadamard_multiply(poly.lu(), poly.ru()).iter().sum::<Scalar>(), poly.tu());
+        assert_eq!(poly.commit_c(), multiscalar_mul(&[(poly.lu(), &points.g_basis()[..8]), (poly.ru(), poly.y_inv_h())]));
+    }
+
+    #[test]
+    #[should_panic(expected = "9 scalars for 8 basis generators")]
+    fn rejects_more_bits_than_generators() {
+        ASVcommitment::compute(256, 8, &Salts::init(), &GlobalPoints::gen_global(8));
+    }
+}
@@ -0,0 +1,659 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use rand_core::OsRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::bullerproof::InnerProductProof;
+use crate::errors::ProofError;
+use crate::generator::{powers_gen, GlobalPoints};
+use crate::operations::{hadamard_multiply, inner_product, inv_vector, points_hadamard_multiply};
+use crate::serialization::{write_points, write_scalars, Reader};
+use crate::text::{armor_plain, dearmor_plain, from_hex, to_hex};
+use crate::transcript::Transcript;
+#[cfg(feature = "serde")]
+use crate::text::{hex_point, hex_point_array, hex_scalar};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variable {
+    Committed(usize),
+    MultiplierLeft(usize),
+    MultiplierRight(usize),
+    MultiplierOutput(usize),
+    One,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LinearCombination {
+    terms: Vec<(Variable, Scalar)>,
+}
+
+// Gadgets are written once against this trait and run unchanged by both the prover and the verifier.
+pub trait ConstraintSystem {
+    fn multiply(&mut self, left: LinearCombination, right: LinearCombination) -> (Variable, Variable, Variable);
+    fn allocate_multiplier(&mut self, assignment: Option<(Scalar, Scalar)>) -> Result<(Variable, Variable, Variable), ProofError>;
+    fn constrain(&mut self, lc: LinearCombination);
+    fn multipliers_len(&self) -> usize;
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct R1CSProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_ai: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_ao: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_s: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point_array"))]
+    commit_t: [RistrettoPoint; 5],
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    tu: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_t: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_lr: Scalar,
+    ipp: InnerProductProof,
+}
+
+pub struct R1CSProver<'a> {
+    transcript: &'a mut Transcript,
+    points: &'a GlobalPoints,
+    constraints: Vec<LinearCombination>,
+    a_l: Vec<Scalar>,
+    a_r: Vec<Scalar>,
+    a_o: Vec<Scalar>,
+    v: Vec<Scalar>,
+    v_blinding: Vec<Scalar>,
+}
+
+pub struct R1CSVerifier<'a> {
+    transcript: &'a mut Transcript,
+    points: &'a GlobalPoints,
+    constraints: Vec<LinearCombination>,
+    num_vars: usize,
+    commit_v: Vec<RistrettoPoint>,
+}
+
+impl LinearCombination {
+    pub fn terms(&self) -> Vec<(Variable, Scalar)> {
+        self.terms.clone()
+    }
+}
+
+impl From<Variable> for LinearCombination {
+    fn from(var: Variable) -> Self {
+        Self { terms: vec![(var, Scalar::ONE)] }
+    }
+}
+
+impl From<Scalar> for LinearCombination {
+    fn from(scalar: Scalar) -> Self {
+        Self { terms: vec![(Variable::One, scalar)] }
+    }
+}
+
+impl From<u64> for LinearCombination {
+    fn from(value: u64) -> Self {
+        Scalar::from(value).into()
+    }
+}
+
+impl<L: Into<LinearCombination>> Add<L> for LinearCombination {
+    type Output = Self;
+
+    fn add(mut self, rhs: L) -> Self {
+        self.terms.extend(rhs.into().terms);
+        self
+    }
+}
+
+impl<L: Into<LinearCombination>> Sub<L> for LinearCombination {
+    type Output = Self;
+
+    fn sub(mut self, rhs: L) -> Self {
+        self.terms.extend(rhs.into().terms.into_iter().map(|(var, coeff)| (var, -coeff)));
+        self
+    }
+}
+
+impl Neg for LinearCombination {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self { terms: self.terms.into_iter().map(|(var, coeff)| (var, -coeff)).collect() }
+    }
+}
+
+impl Mul<Scalar> for LinearCombination {
+    type Output = Self;
+
+    fn mul(self, rhs: Scalar) -> Self {
+        Self { terms: self.terms.into_iter().map(|(var, coeff)| (var, coeff * rhs)).collect() }
+    }
+}
+
+impl<L: Into<LinearCombination>> Add<L> for Variable {
+    type Output = LinearCombination;
+
+    fn add(self, rhs: L) -> LinearCombination {
+        LinearCombination::from(self) + rhs
+    }
+}
+
+impl<L: Into<LinearCombination>> Sub<L> for Variable {
+    type Output = LinearCombination;
+
+    fn sub(self, rhs: L) -> LinearCombination {
+        LinearCombination::from(self) - rhs
+    }
+}
+
+impl Mul<Scalar> for Variable {
+    type Output = LinearCombination;
+
+    fn mul(self, rhs: Scalar) -> LinearCombination {
+        LinearCombination::from(self) * rhs
+    }
+}
+
+impl R1CSProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_points(&mut bytes, &[self.commit_ai, self.commit_ao, self.commit_s]);
+        write_points(&mut bytes, &self.commit_t);
+        write_scalars(&mut bytes, &[self.tu, self.pi_t, self.pi_lr]);
+        bytes.extend(self.ipp.to_bytes());
+
+        bytes
+    }
+
+    // The number of folding rounds follows from the length, as the circuit size is not fixed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = Reader::new(bytes);
+        let [commit_ai, commit_ao, commit_s] = [reader.read_point()?, reader.read_point()?, reader.read_point()?];
+        let commit_t = [reader.read_point()?, reader.read_point()?, reader.read_point()?, reader.read_point()?, reader.read_point()?];
+        let [tu, pi_t, pi_lr] = [reader.read_scalar()?, reader.read_scalar()?, reader.read_scalar()?];
+        let rounds = reader.rounds(64)?;
+        let ipp = InnerProductProof::read(&mut reader, rounds)?;
+        reader.finish()?;
+
+        Ok(Self {
+            commit_ai,
+            commit_ao,
+            commit_s,
+            commit_t,
+            tu,
+            pi_t,
+            pi_lr,
+            ipp,
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?)
+    }
+
+    pub fn to_armored(&self) -> String {
+        armor_plain("R1CSProof", &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<Self, ProofError> {
+        Self::from_bytes(&dearmor_plain(text, "R1CSProof")?)
+    }
+
+    pub fn commit_ai(&self) -> RistrettoPoint {
+        self.commit_ai
+    }
+
+    pub fn commit_ao(&self) -> RistrettoPoint {
+        self.commit_ao
+    }
+
+    pub fn commit_s(&self) -> RistrettoPoint {
+        self.commit_s
+    }
+
+    pub fn commit_t(&self) -> [RistrettoPoint; 5] {
+        self.commit_t
+    }
+
+    pub fn tu(&self) -> Scalar {
+        self.tu
+    }
+
+    pub fn pi_t(&self) -> Scalar {
+        self.pi_t
+    }
+
+    pub fn pi_lr(&self) -> Scalar {
+        self.pi_lr
+    }
+
+    pub fn ipp(&self) -> &InnerProductProof {
+        &self.ipp
+    }
+}
+
+impl<'a> R1CSProver<'a> {
+    pub fn new(points: &'a GlobalPoints, transcript: &'a mut Transcript) -> Self {
+        transcript.append_message(b"dom-sep", b"r1cs v1");
+
+        Self {
+            transcript,
+            points,
+            constraints: vec![],
+            a_l: vec![],
+            a_r: vec![],
+            a_o: vec![],
+            v: vec![],
+            v_blinding: vec![],
+        }
+    }
+
+    pub fn commit(&mut self, v: Scalar, gamma: Scalar) -> (RistrettoPoint, Variable) {
+        let commit_v = self.points.commit(&v, &gamma);
+        self.transcript.append_point(b"V", &commit_v);
+        self.v.push(v);
+        self.v_blinding.push(gamma);
+
+        (commit_v, Variable::Committed(self.v.len() - 1))
+    }
+
+    fn eval(&self, lc: &LinearCombination) -> Result<Scalar, ProofError> {
+        lc.terms.iter().map(|(var, coeff)| {
+            let value = match var {
+                Variable::Committed(i) => self.v.get(*i),
+                Variable::MultiplierLeft(i) => self.a_l.get(*i),
+                Variable::MultiplierRight(i) => self.a_r.get(*i),
+                Variable::MultiplierOutput(i) => self.a_o.get(*i),
+                Variable::One => Some(&Scalar::ONE),
+            };
+            value.map(|value| coeff * value).ok_or(ProofError::InvalidVariable)
+        }).sum()
+    }
+
+    pub fn prove(self) -> Result<R1CSProof, ProofError> {
+        let n = self.a_l.len();
+        let padded_n = n.next_power_of_two();
+        if self.points.g_basis().len() < padded_n {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        for lc in self.constraints.iter() {
+            if self.eval(lc)? != Scalar::ZERO {
+                return Err(ProofError::UnsatisfiedConstraint);
+            }
+        }
+        let g_basis = self.points.g_basis()[..padded_n].to_vec();
+        let h_basis = self.points.h_basis()[..padded_n].to_vec();
+        self.transcript.append_u64(b"n", n as u64);
+
+        let mut rng = OsRng;
+        let [salt_ai, salt_ao, salt_s] = [(); 3].map(|_| Scalar::random(&mut rng));
+        let sl: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        let sr: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+
+        let commit_ai = self.points.vector_commit([&self.a_l, &self.a_r], &salt_ai);
+        let commit_ao = self.points.vector_commit([&self.a_o, &[]], &salt_ao);
+        let commit_s = self.points.vector_commit([&sl, &sr], &salt_s);
+        self.transcript.append_point(b"A_I", &commit_ai);
+        self.transcript.append_point(b"A_O", &commit_ao);
+        self.transcript.append_point(b"S", &commit_s);
+
+        let y = self.transcript.challenge_scalar(b"y");
+        let z = self.transcript.challenge_scalar(b"z");
+        let yn = powers_gen(y, padded_n);
+        let y_inv = inv_vector(&yn);
+        let ([w_l, w_r, w_o, w_v], _) = flatten_constraints(&self.constraints, z, n, self.v.len())?;
+
+        // l(X) = l1 X + l2 X^2 + l3 X^3, r(X) = r0 + r1 X + r3 X^3
+        let l1: Vec<Scalar> = (0..n).map(|i| self.a_l[i] + (y_inv[i] * w_r[i])).collect();
+        let l2 = self.a_o.clone();
+        let l3 = sl;
+        let r0: Vec<Scalar> = (0..n).map(|i| w_o[i] - yn[i]).collect();
+        let r1: Vec<Scalar> = (0..n).map(|i| (yn[i] * self.a_r[i]) + w_l[i]).collect();
+        let r3: Vec<Scalar> = (0..n).map(|i| yn[i] * sr[i]).collect();
+
+        let ip = |a: &[Scalar], b: &[Scalar]| hadamard_multiply(a, b).iter().sum::<Scalar>();
+        let tx = [
+            ip(&l1, &r0),
+            ip(&l1, &r1) + ip(&l2, &r0),
+            ip(&l2, &r1) + ip(&l3, &r0),
+            ip(&l1, &r3) + ip(&l3, &r1),
+            ip(&l2, &r3),
+            ip(&l3, &r3),
+        ];
+        let mut taus = [(); 6].map(|_| Scalar::random(&mut rng));
+        let commit_t = [0, 2, 3, 4, 5].map(|i| self.points.commit(&tx[i], &taus[i]));
+        for commit in commit_t.iter() {
+            self.transcript.append_point(b"T", commit);
+        }
+
+        let x = self.transcript.challenge_scalar(b"x");
+        let xn = powers_gen(x, 7);
+        taus[1] = ip(&w_v, &self.v_blinding);
+        let tu: Scalar = (0..6).map(|i| tx[i] * xn[i + 1]).sum();
+        let pi_t: Scalar = (0..6).map(|i| taus[i] * xn[i + 1]).sum();
+        let pi_lr = (salt_ai * x) + (salt_ao * xn[2]) + (salt_s * xn[3]);
+
+        let mut l: Vec<Scalar> = (0..n).map(|i| (l1[i] * x) + (l2[i] * xn[2]) + (l3[i] * xn[3])).collect();
+        let mut r: Vec<Scalar> = (0..n).map(|i| r0[i] + (r1[i] * x) + (r3[i] * xn[3])).collect();
+        l.resize(padded_n, Scalar::ZERO);
+        r.extend(yn[n..].iter().map(|y| -y));
+
+        self.transcript.append_scalar(b"t_x", &tu);
+        self.transcript.append_scalar(b"t_x_blinding", &pi_t);
+        self.transcript.append_scalar(b"e_blinding", &pi_lr);
+        let q = self.points.mul_g_i(&self.transcript.challenge_scalar(b"w"));
+
+        let y_inv_h = points_hadamard_multiply(&y_inv, &h_basis);
+        let ipp = InnerProductProof::create(self.transcript, [g_basis, y_inv_h], q, [l, r]);
+
+        Ok(R1CSProof {
+            commit_ai,
+            commit_ao,
+            commit_s,
+            commit_t,
+            tu,
+            pi_t,
+            pi_lr,
+            ipp,
+        })
+    }
+}
+
+impl ConstraintSystem for R1CSProver<'_> {
+    fn multiply(&mut self, mut left: LinearCombination, mut right: LinearCombination) -> (Variable, Variable, Variable) {
+        // An unknown variable evaluates to zero here and is reported by prove(), which
+        // re-evaluates every constraint before committing to anything.
+        let [l, r] = [&left, &right].map(|lc| self.eval(lc).unwrap_or(Scalar::ZERO));
+        let i = self.a_l.len();
+        self.a_l.push(l);
+        self.a_r.push(r);
+        self.a_o.push(l * r);
+
+        let vars = (Variable::MultiplierLeft(i), Variable::MultiplierRight(i), Variable::MultiplierOutput(i));
+        left.terms.push((vars.0, -Scalar::ONE));
+        right.terms.push((vars.1, -Scalar::ONE));
+        self.constrain(left);
+        self.constrain(right);
+
+        vars
+    }
+
+    fn allocate_multiplier(&mut self, assignment: Option<(Scalar, Scalar)>) -> Result<(Variable, Variable, Variable), ProofError> {
+        let (l, r) = assignment.ok_or(ProofError::MissingAssignment)?;
+        let i = self.a_l.len();
+        self.a_l.push(l);
+        self.a_r.push(r);
+        self.a_o.push(l * r);
+
+        Ok((Variable::MultiplierLeft(i), Variable::MultiplierRight(i), Variable::MultiplierOutput(i)))
+    }
+
+    fn constrain(&mut self, lc: LinearCombination) {
+        self.constraints.push(lc);
+    }
+
+    fn multipliers_len(&self) -> usize {
+        self.a_l.len()
+    }
+}
+
+impl<'a> R1CSVerifier<'a> {
+    pub fn new(points: &'a GlobalPoints, transcript: &'a mut Transcript) -> Self {
+        transcript.append_message(b"dom-sep", b"r1cs v1");
+
+        Self {
+            transcript,
+            points,
+            constraints: vec![],
+            num_vars: 0,
+            commit_v: vec![],
+        }
+    }
+
+    pub fn commit(&mut self, commit_v: RistrettoPoint) -> Variable {
+        self.transcript.append_point(b"V", &commit_v);
+        self.commit_v.push(commit_v);
+
+        Variable::Committed(self.commit_v.len() - 1)
+    }
+
+    pub fn verify(self, proof: &R1CSProof) -> Result<(), ProofError> {
+        let n = self.num_vars;
+        let padded_n = n.next_power_of_two();
+        if self.points.g_basis().len() < padded_n {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        let g_basis = self.points.g_basis()[..padded_n].to_vec();
+        let h_basis = self.points.h_basis()[..padded_n].to_vec();
+        let [g_i, b_i] = [self.points.g_i(), self.points.b_i()];
+        self.transcript.append_u64(b"n", n as u64);
+
+        self.transcript.append_point(b"A_I", &proof.commit_ai);
+        self.transcript.append_point(b"A_O", &proof.commit_ao);
+        self.transcript.append_point(b"S", &proof.commit_s);
+        let y = self.transcript.challenge_scalar(b"y");
+        let z = self.transcript.challenge_scalar(b"z");
+        for commit in proof.commit_t.iter() {
+            self.transcript.append_point(b"T", commit);
+        }
+        let x = self.transcript.challenge_scalar(b"x");
+        self.transcript.append_scalar(b"t_x", &proof.tu);
+        self.transcript.append_scalar(b"t_x_blinding", &proof.pi_t);
+        self.transcript.append_scalar(b"e_blinding", &proof.pi_lr);
+        let q = self.transcript.challenge_scalar(b"w") * g_i;
+
+        let yn = powers_gen(y, padded_n);
+        let y_inv = inv_vector(&yn);
+        let xn = powers_gen(x, 7);
+        let ([mut w_l, mut w_r, mut w_o, w_v], w_c) = flatten_constraints(&self.constraints, z, n, self.commit_v.len())?;
+        let delta: Scalar = (0..n).map(|i| y_inv[i] * w_r[i] * w_l[i]).sum();
+
+        let [commit_t1, commit_t3, commit_t4, commit_t5, commit_t6] = proof.commit_t;
+        let eqn1lhs = (proof.tu * g_i) + (proof.pi_t * b_i);
+        let eqn1rhs = ((w_c + delta) * xn[2] * g_i)
+            + self.commit_v.iter().zip(w_v.iter()).map(|(v, w)| v * (w * xn[2])).sum::<RistrettoPoint>()
+            + (commit_t1 * x) + (commit_t3 * xn[3]) + (commit_t4 * xn[4]) + (commit_t5 * xn[5]) + (commit_t6 * xn[6]);
+        if eqn1lhs != eqn1rhs {
+            return Err(ProofError::VerificationError);
+        }
+
+        for w in [&mut w_l, &mut w_r, &mut w_o] {
+            w.resize(padded_n, Scalar::ZERO);
+        }
+        let g_scalars: Vec<Scalar> = (0..padded_n).map(|i| x * y_inv[i] * w_r[i]).collect();
+        let h_scalars: Vec<Scalar> = (0..padded_n).map(|i| (y_inv[i] * ((x * w_l[i]) + w_o[i])) - Scalar::ONE).collect();
+        let commit_p = (proof.commit_ai * x) + (proof.commit_ao * xn[2]) + (proof.commit_s * xn[3]) - (proof.pi_lr * b_i)
+            + inner_product(&g_scalars, &g_basis) + inner_product(&h_scalars, &h_basis) + (proof.tu * q);
+
+        let y_inv_h = points_hadamard_multiply(&y_inv, &h_basis);
+        proof.ipp.verify(self.transcript, commit_p, [g_basis, y_inv_h], q)
+    }
+}
+
+impl ConstraintSystem for R1CSVerifier<'_> {
+    fn multiply(&mut self, mut left: LinearCombination, mut right: LinearCombination) -> (Variable, Variable, Variable) {
+        let i = self.num_vars;
+        self.num_vars += 1;
+
+        let vars = (Variable::MultiplierLeft(i), Variable::MultiplierRight(i), Variable::MultiplierOutput(i));
+        left.terms.push((vars.0, -Scalar::ONE));
+        right.terms.push((vars.1, -Scalar::ONE));
+        self.constrain(left);
+        self.constrain(right);
+
+        vars
+    }
+
+    fn allocate_multiplier(&mut self, _: Option<(Scalar, Scalar)>) -> Result<(Variable, Variable, Variable), ProofError> {
+        let i = self.num_vars;
+        self.num_vars += 1;
+
+        Ok((Variable::MultiplierLeft(i), Variable::MultiplierRight(i), Variable::MultiplierOutput(i)))
+    }
+
+    fn constrain(&mut self, lc: LinearCombination) {
+        self.constraints.push(lc);
+    }
+
+    fn multipliers_len(&self) -> usize {
+        self.num_vars
+    }
+}
+
+// Collapses all constraints into <w_l, a_l> + <w_r, a_r> + <w_o, a_o> = <w_v, v> + w_c, weighting the q-th constraint by z^(q+1).
+fn flatten_constraints(constraints: &[LinearCombination], z: Scalar, n: usize, m: usize) -> Result<([Vec<Scalar>; 4], Scalar), ProofError> {
+    let [mut w_l, mut w_r, mut w_o] = [(); 3].map(|_| vec![Scalar::ZERO; n]);
+    let mut w_v = vec![Scalar::ZERO; m];
+    let mut w_c = Scalar::ZERO;
+    let mut exp_z = z;
+
+    for lc in constraints {
+        for (var, coeff) in lc.terms.iter() {
+            let (weight, term) = match var {
+                Variable::MultiplierLeft(i) => (w_l.get_mut(*i), exp_z * coeff),
+                Variable::MultiplierRight(i) => (w_r.get_mut(*i), exp_z * coeff),
+                Variable::MultiplierOutput(i) => (w_o.get_mut(*i), exp_z * coeff),
+                Variable::Committed(i) => (w_v.get_mut(*i), -(exp_z * coeff)),
+                Variable::One => (Some(&mut w_c), -(exp_z * coeff)),
+            };
+            *weight.ok_or(ProofError::InvalidVariable)? += term;
+        }
+        exp_z *= z;
+    }
+
+    Ok(([w_l, w_r, w_o, w_v], w_c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand::SeedableRng;
+    use crate::errors::DecodeError;
+
+    // Proves a * b = c for three committed values.
+    fn prove_product(points: &GlobalPoints, [a, b, c]: [u64; 3]) -> Result<(R1CSProof, Vec<RistrettoPoint>), ProofError> {
+        let mut transcript = Transcript::new(b"test");
+        let mut prover = R1CSProver::new(points, &mut transcript);
+        let (commits, vars): (Vec<_>, Vec<_>) = [a, b, c].iter()
+            .map(|v| prover.commit(Scalar::from(*v), Scalar::random(&mut OsRng)))
+            .unzip();
+        let (_, _, out) = prover.multiply(vars[0].into(), vars[1].into());
+        prover.constrain(out - vars[2]);
+
+        Ok((prover.prove()?, commits))
+    }
+
+    fn verify_product(points: &GlobalPoints, label: &[u8], proof: &R1CSProof, commits: &[RistrettoPoint]) -> Result<(), ProofError> {
+        let mut transcript = Transcript::new(label);
+        let mut verifier = R1CSVerifier::new(points, &mut transcript);
+        let vars: Vec<Variable> = commits.iter().map(|commit| verifier.commit(*commit)).collect();
+        let (_, _, out) = verifier.multiply(vars[0].into(), vars[1].into());
+        verifier.constrain(out - vars[2]);
+
+        verifier.verify(proof)
+    }
+
+    #[test]
+    fn prove_verify_round_trip() {
+        let points = GlobalPoints::gen_global(8);
+        let (proof, commits) = prove_product(&points, [3, 5, 15]).unwrap();
+
+        assert_eq!(verify_product(&points, b"test", &proof, &commits), Ok(()));
+    }
+
+    #[test]
+    fn rejects_wrong_commitment() {
+        let points = GlobalPoints::gen_global(8);
+        let (proof, mut commits) = prove_product(&points, [3, 5, 15]).unwrap();
+        commits[2] += points.g_i();
+
+        assert!(verify_product(&points, b"test", &proof, &commits).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_transcript() {
+        let points = GlobalPoints::gen_global(8);
+        let (proof, commits) = prove_product(&points, [3, 5, 15]).unwrap();
+
+        assert!(verify_product(&points, b"other", &proof, &commits).is_err());
+    }
+
+    #[test]
+    fn unsatisfied_witness_is_an_error() {
+        let points = GlobalPoints::gen_global(8);
+
+        assert!(matches!(prove_product(&points, [3, 5, 16]), Err(ProofError::UnsatisfiedConstraint)));
+    }
+
+    #[test]
+    fn unknown_variable_is_an_error() {
+        let points = GlobalPoints::gen_global(8);
+        let mut transcript = Transcript::new(b"test");
+        let mut prover = R1CSProver::new(&points, &mut transcript);
+        prover.constrain(Variable::Committed(0) - 1u64);
+        assert!(matches!(prover.prove(), Err(ProofError::InvalidVariable)));
+
+        let (proof, commits) = prove_product(&points, [3, 5, 15]).unwrap();
+        let mut transcript = Transcript::new(b"test");
+        let mut verifier = R1CSVerifier::new(&points, &mut transcript);
+        let vars: Vec<Variable> = commits.iter().map(|commit| verifier.commit(*commit)).collect();
+        let (_, _, out) = verifier.multiply(vars[0].into(), vars[1].into());
+        verifier.constrain(out - Variable::Committed(3));
+        assert_eq!(verifier.verify(&proof), Err(ProofError::InvalidVariable));
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let points = GlobalPoints::gen_global(8);
+        let (proof, commits) = prove_product(&points, [3, 5, 15]).unwrap();
+        let from_bytes = R1CSProof::from_bytes(&proof.to_bytes()).unwrap();
+        let from_hex = R1CSProof::from_hex(&proof.to_hex()).unwrap();
+        let from_armor = R1CSProof::from_armored(&proof.to_armored()).unwrap();
+
+        assert_eq!(from_bytes.to_bytes(), proof.to_bytes());
+        assert_eq!(from_hex.to_bytes(), proof.to_bytes());
+        assert_eq!(verify_product(&points, b"test", &from_armor, &commits), Ok(()));
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        let points = GlobalPoints::gen_global(8);
+        let (proof, _) = prove_product(&points, [3, 5, 15]).unwrap();
+        let bytes = proof.to_bytes();
+        let mut scalar = bytes.clone();
+        scalar[8 * 32..9 * 32].copy_from_slice(&[0xff; 32]);
+
+        assert_eq!(R1CSProof::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(DecodeError::InvalidLength.into()));
+        assert_eq!(R1CSProof::from_bytes(&scalar).err(), Some(DecodeError::NonCanonicalScalar.into()));
+        assert_eq!(R1CSProof::from_bytes(&bytes[..10 * 32]).err(), Some(DecodeError::UnexpectedEnd.into()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let points = GlobalPoints::gen_global(8);
+        let (proof, commits) = prove_product(&points, [3, 5, 15]).unwrap();
+        let decoded: R1CSProof = serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+
+        assert_eq!(decoded.to_bytes(), proof.to_bytes());
+        assert_eq!(verify_product(&points, b"test", &decoded, &commits), Ok(()));
+    }
+
+    #[test]
+    fn proofs_with_tables_verify_without() {
+        let tabled = GlobalPoints::from_rng(8, &mut ChaCha20Rng::from_seed([3; 32])).with_tables();
+        let plain = GlobalPoints::from_rng(8, &mut ChaCha20Rng::from_seed([3; 32]));
+        let (proof, commits) = prove_product(&tabled, [3, 5, 15]).unwrap();
+
+        assert_eq!(verify_product(&plain, b"test", &proof, &commits), Ok(()));
+    }
+}
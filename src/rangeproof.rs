@@ -0,0 +1,556 @@
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::bullerproof::InnerProductProof;
+use crate::bulletproofs_plus::BulletproofsPlusProof;
+use crate::reciprocal::ReciprocalRangeProof;
+use crate::errors::{DecodeError, ProofError};
+use crate::generator::GlobalPoints;
+use crate::metrics::{Metrics, ProofPhase};
+use crate::operations::{multiscalar_mul, point_mul, vec_scalar_mul, vector_add};
+use crate::prover::{ASVcommitment, Opening, Polycommitment, Salts, T1T2commitment};
+use crate::serialization::{write_points, write_scalars, Reader};
+use crate::text::{armor, dearmor, from_hex, to_hex};
+use crate::transcript::Transcript;
+use crate::verifier::{Generatives, VerifierKey};
+#[cfg(feature = "serde")]
+use crate::text::{hex_point, hex_scalar};
+
+// Non-interactive form of the prover/verifier flow in main.rs, with y, z, u and the
+// folding challenges drawn from a transcript instead of from the verifier.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RangeProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_a: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_s: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_t1: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_t2: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    tu: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_lr: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_t: Scalar,
+    ipp: InnerProductProof,
+}
+
+impl RangeProof {
+    pub fn prove(transcript: &mut Transcript, points: &GlobalPoints, v: u64, range: usize, salt: Salts) -> Result<(RangeProof, RistrettoPoint), ProofError> {
+        RangeProof::prove_with_metrics(transcript, points, v, range, salt, &mut Metrics::new())
+    }
+
+    // Same as prove, recording time and operation counts of every phase in `metrics`.
+    pub fn prove_with_metrics(transcript: &mut Transcript, points: &GlobalPoints, v: u64, range: usize, salt: Salts, metrics: &mut Metrics) -> Result<(RangeProof, RistrettoPoint), ProofError> {
+        check_range(points, range)?;
+        if range < 64 && v >> range != 0 {
+            return Err(ProofError::ValueOutOfRange);
+        }
+
+        let asv = metrics.measure(ProofPhase::Commitment, || ASVcommitment::compute(v, range, &salt, points));
+        let [commit_a, commit_s, commit_v] = asv.to_verifier();
+        let [y, z] = append_commitments(transcript, range, [commit_v, commit_a, commit_s]);
+
+        let t1t2 = metrics.measure(ProofPhase::T1T2, || T1T2commitment::init(&salt, &asv, y, z, range, points));
+        let [commit_t1, commit_t2] = [t1t2.commit_t1(), t1t2.commit_t2()];
+        let u = append_t_commitments(transcript, [commit_t1, commit_t2]);
+
+        let poly = metrics.measure(ProofPhase::Polynomial, || Polycommitment::compute(u, salt, &asv, &t1t2, y, z, range, points));
+        let q = points.mul_g_i(&append_openings(transcript, [poly.tu(), poly.pi_t(), poly.pi_lr()]));
+
+        let g_basis = points.g_basis()[..range].to_vec();
+        let ipp = InnerProductProof::create_with_metrics(transcript, [g_basis, poly.y_inv_h().to_vec()], q, [poly.lu().to_vec(), poly.ru().to_vec()], metrics);
+
+        Ok((RangeProof {
+            commit_a,
+            commit_s,
+            commit_t1,
+            commit_t2,
+            tu: poly.tu(),
+            pi_lr: poly.pi_lr(),
+            pi_t: poly.pi_t(),
+            ipp,
+        }, commit_v))
+    }
+
+    // For provers that build the proof themselves, such as fixed::Prover.
+    pub(crate) fn from_parts([commit_a, commit_s, commit_t1, commit_t2]: [RistrettoPoint; 4], [tu, pi_lr, pi_t]: [Scalar; 3], ipp: InnerProductProof) -> Self {
+        Self {
+            commit_a,
+            commit_s,
+            commit_t1,
+            commit_t2,
+            tu,
+            pi_lr,
+            pi_t,
+            ipp,
+        }
+    }
+
+    // For commitments created elsewhere: only gamma is taken from the caller, the rest of the salts are fresh.
+    pub fn prove_opening(transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, commit_v: RistrettoPoint, range: usize) -> Result<RangeProof, ProofError> {
+        if opening.commit(points) != commit_v {
+            return Err(ProofError::OpeningMismatch);
+        }
+        let (proof, _) = RangeProof::prove(transcript, points, opening.value(), range, Salts::with_gamma(opening.gamma()))?;
+
+        Ok(proof)
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, range: usize) -> Result<(), ProofError> {
+        self.verify_with_metrics(transcript, points, commit_v, range, &mut Metrics::new())
+    }
+
+    pub fn verify_with_metrics(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, range: usize, metrics: &mut Metrics) -> Result<(), ProofError> {
+        check_range(points, range)?;
+
+        metrics.measure(ProofPhase::Verification, || self.verify_equations(transcript, &VerifierKey::new(range, points)?, commit_v))
+    }
+
+    // For verifying many proofs of one bit width against the same generators.
+    pub fn verify_with_key(&self, transcript: &mut Transcript, key: &VerifierKey, commit_v: RistrettoPoint) -> Result<(), ProofError> {
+        if key.parties() != 1 {
+            return Err(ProofError::InvalidAggregation);
+        }
+        check_bitsize(key.range())?;
+
+        self.verify_equations(transcript, key, commit_v)
+    }
+
+    fn verify_equations(&self, transcript: &mut Transcript, key: &VerifierKey, commit_v: RistrettoPoint) -> Result<(), ProofError> {
+        let [y, z] = append_commitments(transcript, key.range(), [commit_v, self.commit_a, self.commit_s]);
+        let u = append_t_commitments(transcript, [self.commit_t1, self.commit_t2]);
+        let q = key.mul_g_i(&append_openings(transcript, [self.tu, self.pi_t, self.pi_lr]));
+
+        let gen = Generatives::from_key([y, z], u, key);
+        let z2 = z * z;
+        let delta = key.delta(gen.yn().iter().sum(), z);
+
+        let eqn3lhs = key.mul_g_i(&self.tu) + key.mul_b_i(&self.pi_t);
+        let eqn3rhs = multiscalar_mul(&[(&[z2, u, u * u], &[commit_v, self.commit_t1, self.commit_t2])]) + key.mul_g_i(&delta);
+        if eqn3lhs != eqn3rhs {
+            return Err(ProofError::VerificationError);
+        }
+
+        let h_weights = vector_add(&vec_scalar_mul(gen.yn(), &z), &vec_scalar_mul(key.n2(), &z2));
+        let commit_c = multiscalar_mul(&[(&[Scalar::ONE, u], &[self.commit_a, self.commit_s]), (&h_weights, gen.y_inv_h())])
+            + key.g_term(z) - key.mul_b_i(&self.pi_lr);
+
+        self.ipp.verify(transcript, commit_c + point_mul(&self.tu, &q), [key.g_basis().to_vec(), gen.y_inv_h().to_vec()], q)
+    }
+
+    // A, S, T1, T2, t, pi_t, pi_lr followed by the inner-product proof
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_points(&mut bytes, &[self.commit_a, self.commit_s, self.commit_t1, self.commit_t2]);
+        write_scalars(&mut bytes, &[self.tu, self.pi_t, self.pi_lr]);
+        bytes.extend(self.ipp.to_bytes());
+
+        bytes
+    }
+
+    // The bit width fixes the number of folding rounds, so it is needed to decode.
+    pub fn from_bytes(bytes: &[u8], range: usize) -> Result<Self, ProofError> {
+        check_bitsize(range)?;
+
+        RangeProof::read_body(Reader::new(bytes), range)
+    }
+
+    // Reads one proof off the front of `reader`, which may hold more after it.
+    pub(crate) fn read(reader: &mut Reader, range: usize) -> Result<Self, ProofError> {
+        check_bitsize(range)?;
+        let rounds = range.trailing_zeros() as usize;
+
+        RangeProof::read_body(reader.take((7 * 32) + ((rounds + 1) * 64))?, range)
+    }
+
+    fn read_body(mut reader: Reader, range: usize) -> Result<Self, ProofError> {
+        let [commit_a, commit_s, commit_t1, commit_t2] = [reader.read_point()?, reader.read_point()?, reader.read_point()?, reader.read_point()?];
+        let [tu, pi_t, pi_lr] = [reader.read_scalar()?, reader.read_scalar()?, reader.read_scalar()?];
+        let ipp = InnerProductProof::read(&mut reader, range.trailing_zeros() as usize)?;
+        reader.finish()?;
+
+        Ok(Self {
+            commit_a,
+            commit_s,
+            commit_t1,
+            commit_t2,
+            tu,
+            pi_lr,
+            pi_t,
+            ipp,
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str, range: usize) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?, range)
+    }
+
+    // Armored text carries the bit width in its header, so unlike from_bytes it is self-describing.
+    pub fn to_armored(&self, range: usize) -> String {
+        armor("RangeProof", range, &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<(Self, usize), ProofError> {
+        let (range, bytes) = dearmor(text, "RangeProof")?;
+
+        Ok((Self::from_bytes(&bytes, range)?, range))
+    }
+
+    pub fn commit_a(&self) -> RistrettoPoint {
+        self.commit_a
+    }
+
+    pub fn commit_s(&self) -> RistrettoPoint {
+        self.commit_s
+    }
+
+    pub fn commit_t1(&self) -> RistrettoPoint {
+        self.commit_t1
+    }
+
+    pub fn commit_t2(&self) -> RistrettoPoint {
+        self.commit_t2
+    }
+
+    pub fn tu(&self) -> Scalar {
+        self.tu
+    }
+
+    pub fn pi_lr(&self) -> Scalar {
+        self.pi_lr
+    }
+
+    pub fn pi_t(&self) -> Scalar {
+        self.pi_t
+    }
+
+    pub fn ipp(&self) -> &InnerProductProof {
+        &self.ipp
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+pub enum ProofMode {
+    Bulletproof,
+    BulletproofsPlus,
+    Reciprocal,
+}
+
+// Leads every self-describing proof encoding (RangeProofVariant, AggregatedRangeProof), ahead of the mode byte.
+pub const PROTOCOL_VERSION: u8 = 1;
+
+// A range proof in either protocol, chosen per proof; the serialized form leads with the
+// protocol version and a mode byte.
+#[allow(clippy::large_enum_variant)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(tag = "mode", rename_all = "snake_case"))]
+pub enum RangeProofVariant {
+    Bulletproof(RangeProof),
+    BulletproofsPlus(BulletproofsPlusProof),
+    Reciprocal(ReciprocalRangeProof),
+}
+
+impl RangeProofVariant {
+    pub fn prove(mode: ProofMode, transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, range: usize) -> Result<(RangeProofVariant, RistrettoPoint), ProofError> {
+        match mode {
+            ProofMode::Bulletproof => {
+                let commit_v = opening.commit(points);
+                let proof = RangeProof::prove_opening(transcript, points, opening, commit_v, range)?;
+
+                Ok((RangeProofVariant::Bulletproof(proof), commit_v))
+            }
+            ProofMode::BulletproofsPlus => {
+                let (proof, commit_v) = BulletproofsPlusProof::prove(transcript, points, opening, range)?;
+
+                Ok((RangeProofVariant::BulletproofsPlus(proof), commit_v))
+            }
+            ProofMode::Reciprocal => {
+                let (proof, commit_v) = ReciprocalRangeProof::prove(transcript, points, opening, range)?;
+
+                Ok((RangeProofVariant::Reciprocal(proof), commit_v))
+            }
+        }
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, range: usize) -> Result<(), ProofError> {
+        match self {
+            RangeProofVariant::Bulletproof(proof) => proof.verify(transcript, points, commit_v, range),
+            RangeProofVariant::BulletproofsPlus(proof) => proof.verify(transcript, points, commit_v, range),
+            RangeProofVariant::Reciprocal(proof) => proof.verify(transcript, points, commit_v, range),
+        }
+    }
+
+    pub fn mode(&self) -> ProofMode {
+        match self {
+            RangeProofVariant::Bulletproof(_) => ProofMode::Bulletproof,
+            RangeProofVariant::BulletproofsPlus(_) => ProofMode::BulletproofsPlus,
+            RangeProofVariant::Reciprocal(_) => ProofMode::Reciprocal,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (tag, body) = match self {
+            RangeProofVariant::Bulletproof(proof) => (0u8, proof.to_bytes()),
+            RangeProofVariant::BulletproofsPlus(proof) => (1u8, proof.to_bytes()),
+            RangeProofVariant::Reciprocal(proof) => (2u8, proof.to_bytes()),
+        };
+        let mut bytes = vec![PROTOCOL_VERSION, tag];
+        bytes.extend(body);
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8], range: usize) -> Result<RangeProofVariant, ProofError> {
+        match read_header(bytes)? {
+            (0, body) => Ok(RangeProofVariant::Bulletproof(RangeProof::from_bytes(body, range)?)),
+            (1, body) => Ok(RangeProofVariant::BulletproofsPlus(BulletproofsPlusProof::from_bytes(body, range)?)),
+            (2, body) => Ok(RangeProofVariant::Reciprocal(ReciprocalRangeProof::from_bytes(body, range)?)),
+            (mode, _) => Err(DecodeError::UnknownMode(mode).into()),
+        }
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str, range: usize) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?, range)
+    }
+
+    pub fn to_armored(&self, range: usize) -> String {
+        armor("RangeProofVariant", range, &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<(Self, usize), ProofError> {
+        let (range, bytes) = dearmor(text, "RangeProofVariant")?;
+
+        Ok((Self::from_bytes(&bytes, range)?, range))
+    }
+}
+
+// Checks the version byte and splits off the mode byte that follows it.
+pub(crate) fn read_header(bytes: &[u8]) -> Result<(u8, &[u8]), DecodeError> {
+    match bytes {
+        [PROTOCOL_VERSION, mode, body @ ..] => Ok((*mode, body)),
+        [PROTOCOL_VERSION] | [] => Err(DecodeError::UnexpectedEnd),
+        [version, ..] => Err(DecodeError::UnknownVersion(*version)),
+    }
+}
+
+pub(crate) fn check_range(points: &GlobalPoints, range: usize) -> Result<(), ProofError> {
+    check_bitsize(range)?;
+    if points.g_basis().len() < range {
+        return Err(ProofError::InvalidGeneratorsLength);
+    }
+
+    Ok(())
+}
+
+// The transcript of the single-value range proof, shared by RangeProof's prover and verifier and
+// by fixed::Prover. Absorbs the statement, V, A and S and draws y and z.
+pub(crate) fn append_commitments(transcript: &mut Transcript, range: usize, [commit_v, commit_a, commit_s]: [RistrettoPoint; 3]) -> [Scalar; 2] {
+    transcript.append_message(b"dom-sep", b"rangeproof v1");
+    transcript.append_u64(b"n", range as u64);
+    transcript.append_point(b"V", &commit_v);
+    transcript.append_point(b"A", &commit_a);
+    transcript.append_point(b"S", &commit_s);
+
+    [transcript.challenge_scalar(b"y"), transcript.challenge_scalar(b"z")]
+}
+
+// Absorbs T1 and T2 and draws u.
+pub(crate) fn append_t_commitments(transcript: &mut Transcript, [commit_t1, commit_t2]: [RistrettoPoint; 2]) -> Scalar {
+    transcript.append_point(b"T1", &commit_t1);
+    transcript.append_point(b"T2", &commit_t2);
+
+    transcript.challenge_scalar(b"u")
+}
+
+// Absorbs t, pi_t and pi_lr and draws w, the scalar of the inner-product base.
+pub(crate) fn append_openings(transcript: &mut Transcript, [tu, pi_t, pi_lr]: [Scalar; 3]) -> Scalar {
+    transcript.append_scalar(b"t_x", &tu);
+    transcript.append_scalar(b"t_x_blinding", &pi_t);
+    transcript.append_scalar(b"e_blinding", &pi_lr);
+
+    transcript.challenge_scalar(b"w")
+}
+
+pub(crate) fn check_bitsize(range: usize) -> Result<(), ProofError> {
+    if !range.is_power_of_two() || range > 64 {
+        return Err(ProofError::InvalidBitsize);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prove(value: u64, range: usize) -> (GlobalPoints, RangeProof, RistrettoPoint) {
+        let points = GlobalPoints::gen_global(range);
+        let (proof, commit_v) = RangeProof::prove(&mut Transcript::new(b"test"), &points, value, range, Salts::init()).unwrap();
+
+        (points, proof, commit_v)
+    }
+
+    #[test]
+    fn prove_verify_round_trip() {
+        let (points, proof, commit_v) = prove(200, 8);
+
+        assert_eq!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, 8), Ok(()));
+    }
+
+    #[test]
+    fn rejects_wrong_commitment() {
+        let (points, proof, commit_v) = prove(200, 8);
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v + points.g_i(), 8).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_transcript() {
+        let (points, proof, commit_v) = prove(200, 8);
+
+        assert!(proof.verify(&mut Transcript::new(b"other"), &points, commit_v, 8).is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_proof() {
+        let (points, mut proof, commit_v) = prove(200, 8);
+        proof.tu += Scalar::ONE;
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, 8).is_err());
+    }
+
+    #[test]
+    fn rejects_value_outside_range() {
+        let points = GlobalPoints::gen_global(8);
+        let result = RangeProof::prove(&mut Transcript::new(b"test"), &points, 256, 8, Salts::init());
+
+        assert!(matches!(result, Err(ProofError::ValueOutOfRange)));
+    }
+
+    #[test]
+    fn proves_existing_commitment() {
+        let points = GlobalPoints::gen_global(8);
+        let opening = Opening::random(42);
+        let commit_v = opening.commit(&points);
+        let proof = RangeProof::prove_opening(&mut Transcript::new(b"test"), &points, &opening, commit_v, 8).unwrap();
+
+        assert_eq!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, 8), Ok(()));
+    }
+
+    #[test]
+    fn rejects_opening_of_other_commitment() {
+        let points = GlobalPoints::gen_global(8);
+        let commit_v = Opening::random(42).commit(&points);
+        let result = RangeProof::prove_opening(&mut Transcript::new(b"test"), &points, &Opening::random(42), commit_v, 8);
+
+        assert!(matches!(result, Err(ProofError::OpeningMismatch)));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let (points, proof, commit_v) = prove(200, 8);
+        let bytes = proof.to_bytes();
+        let decoded = RangeProof::from_bytes(&bytes, 8).unwrap();
+
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.verify(&mut Transcript::new(b"test"), &points, commit_v, 8), Ok(()));
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        let (_, proof, _) = prove(200, 8);
+        let bytes = proof.to_bytes();
+        let mut extra = bytes.clone();
+        extra.push(0);
+        let mut scalar = bytes.clone();
+        scalar[4 * 32..5 * 32].copy_from_slice(&[0xff; 32]);
+
+        assert_eq!(RangeProof::from_bytes(&bytes, 16).err(), Some(DecodeError::RoundCountMismatch { expected: 4, found: 3 }.into()));
+        assert_eq!(RangeProof::from_bytes(&extra, 8).err(), Some(DecodeError::TrailingBytes.into()));
+        assert_eq!(RangeProof::from_bytes(&scalar, 8).err(), Some(DecodeError::NonCanonicalScalar.into()));
+        assert_eq!(RangeProof::from_bytes(&bytes, 12).err(), Some(ProofError::InvalidBitsize));
+    }
+
+    #[test]
+    fn variant_bytes_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        for mode in [ProofMode::Bulletproof, ProofMode::BulletproofsPlus, ProofMode::Reciprocal] {
+            let (proof, commit_v) = RangeProofVariant::prove(mode, &mut Transcript::new(b"test"), &points, &Opening::random(200), 8).unwrap();
+            let decoded = RangeProofVariant::from_bytes(&proof.to_bytes(), 8).unwrap();
+
+            assert_eq!(decoded.mode(), mode);
+            assert_eq!(decoded.to_bytes(), proof.to_bytes());
+            assert_eq!(decoded.verify(&mut Transcript::new(b"test"), &points, commit_v, 8), Ok(()));
+        }
+        assert_eq!(RangeProofVariant::from_bytes(&[PROTOCOL_VERSION, 3], 8).err(), Some(DecodeError::UnknownMode(3).into()));
+        assert_eq!(RangeProofVariant::from_bytes(&[2, 0], 8).err(), Some(DecodeError::UnknownVersion(2).into()));
+        assert_eq!(RangeProofVariant::from_bytes(&[PROTOCOL_VERSION], 8).err(), Some(DecodeError::UnexpectedEnd.into()));
+        assert_eq!(RangeProofVariant::from_bytes(&[], 8).err(), Some(DecodeError::UnexpectedEnd.into()));
+    }
+
+    #[test]
+    fn variant_text_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        for mode in [ProofMode::Bulletproof, ProofMode::BulletproofsPlus, ProofMode::Reciprocal] {
+            let (proof, commit_v) = RangeProofVariant::prove(mode, &mut Transcript::new(b"test"), &points, &Opening::random(200), 8).unwrap();
+            let from_hex = RangeProofVariant::from_hex(&proof.to_hex(), 8).unwrap();
+            let (from_armor, range) = RangeProofVariant::from_armored(&proof.to_armored(8)).unwrap();
+
+            assert_eq!(from_hex.to_bytes(), proof.to_bytes());
+            assert_eq!((from_armor.to_bytes(), range), (proof.to_bytes(), 8));
+            assert_eq!(from_armor.verify(&mut Transcript::new(b"test"), &points, commit_v, range), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rejects_text_of_other_proofs() {
+        let (_, proof, _) = prove(200, 8);
+        let variant = RangeProofVariant::from_bytes(&[[PROTOCOL_VERSION, 0].as_slice(), &proof.to_bytes()].concat(), 8).unwrap();
+
+        assert_eq!(RangeProof::from_armored(&variant.to_armored(8)).err(), Some(DecodeError::InvalidArmor.into()));
+        assert_eq!(RangeProof::from_hex(&proof.to_hex().to_uppercase(), 8).err(), Some(DecodeError::InvalidHex.into()));
+        assert_eq!(RangeProof::from_armored(&proof.to_armored(16)).err(), Some(DecodeError::RoundCountMismatch { expected: 4, found: 3 }.into()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn variant_serde_round_trip() {
+        let points = GlobalPoints::gen_global(32);
+        for mode in [ProofMode::Bulletproof, ProofMode::BulletproofsPlus, ProofMode::Reciprocal] {
+            let (proof, commit_v) = RangeProofVariant::prove(mode, &mut Transcript::new(b"test"), &points, &Opening::random(200), 8).unwrap();
+            let json = serde_json::to_string(&proof).unwrap();
+            let decoded: RangeProofVariant = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(decoded.to_bytes(), proof.to_bytes());
+            assert_eq!(decoded.verify(&mut Transcript::new(b"test"), &points, commit_v, 8), Ok(()));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_non_canonical_fields() {
+        let (_, proof, _) = prove(200, 8);
+        let json = serde_json::to_value(&proof).unwrap();
+        let mut upper = json.clone();
+        upper["commit_a"] = json["commit_a"].as_str().unwrap().to_uppercase().into();
+        let mut scalar = json.clone();
+        scalar["tu"] = "ff".repeat(32).into();
+
+        assert!(serde_json::from_value::<RangeProof>(upper).is_err());
+        assert!(serde_json::from_value::<RangeProof>(scalar).is_err());
+        assert!(serde_json::from_value::<RangeProof>(json).is_ok());
+    }
+}
@@ -0,0 +1,434 @@
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use rand_core::OsRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::bullerproof::InnerProductProof;
+use crate::errors::ProofError;
+use crate::generator::{powers_gen, GlobalPoints};
+use crate::metrics::{Metrics, ProofPhase};
+use crate::operations::{hadamard_multiply, inner_product, inv_vector, invert, multiscalar_mul, point_mul, points_hadamard_multiply, vec_scalar_mul, vector_add};
+use crate::prover::Opening;
+use crate::rangeproof::{check_bitsize, check_range};
+use crate::serialization::{write_points, write_scalars, Reader};
+use crate::text::{armor, dearmor, from_hex, to_hex};
+use crate::transcript::Transcript;
+#[cfg(feature = "serde")]
+use crate::text::{hex_point, hex_scalar};
+
+const DIGIT_BITS: usize = 4;
+
+// Range proof over base-16 digits in the style of Bulletproofs++. The value is split into
+// n / 4 digits d_j, and the reciprocal argument sum 1 / (e + d_j) = sum m_i / (e + i) shows
+// every digit is in [0, 16), where m_i counts how often digit i occurs.
+//
+// Vector layout, padded to a power of two:
+//   a_L = [d_0 .. d_k-1 | 0 ..]
+//   a_R = [x / (e + d_0) .. x / (e + d_k-1) | m_0 .. m_15 | 0 ..]
+// D commits to the digits and multiplicities before e is drawn, R to the reciprocals after it.
+// The verifier uses D + x * R, so R cannot move the digits once e is known.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReciprocalRangeProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_d: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_r: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_s: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_t1: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_t2: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    tu: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_t: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_lr: Scalar,
+    ipp: InnerProductProof,
+}
+
+// Digit base, digit count and padded vector size for a bit width.
+struct Layout {
+    base: usize,
+    digits: usize,
+    size: usize,
+}
+
+// Public vectors of the constraint system, fixed once e and x are known:
+// (a_L + shift) o a_R = sigma, <a_L, beta> = v and <a_R, rho> = 0
+struct Constraints {
+    shift: Vec<Scalar>,
+    sigma: Vec<Scalar>,
+    beta: Vec<Scalar>,
+    rho: Vec<Scalar>,
+}
+
+impl ReciprocalRangeProof {
+    pub fn prove(transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, range: usize) -> Result<(Self, RistrettoPoint), ProofError> {
+        ReciprocalRangeProof::prove_with_metrics(transcript, points, opening, range, &mut Metrics::new())
+    }
+
+    // Same as prove, recording time and operation counts of each phase in `metrics`. D, R and S
+    // are measured together as the commitment phase.
+    pub fn prove_with_metrics(transcript: &mut Transcript, points: &GlobalPoints, opening: &Opening, range: usize, metrics: &mut Metrics) -> Result<(Self, RistrettoPoint), ProofError> {
+        let layout = Layout::compute(points, range)?;
+        if range < 64 && opening.value() >> range != 0 {
+            return Err(ProofError::ValueOutOfRange);
+        }
+        let mut rng = OsRng;
+        let g_basis = points.g_basis()[..layout.size].to_vec();
+        let h_basis = points.h_basis()[..layout.size].to_vec();
+
+        let digits: Vec<usize> = (0..layout.digits).map(|j| ((opening.value() >> (j * DIGIT_BITS)) as usize) & (layout.base - 1)).collect();
+        let mut multiplicities = vec![Scalar::ZERO; layout.base];
+        for digit in digits.iter() {
+            multiplicities[*digit] += Scalar::ONE;
+        }
+        let mut al = vec![Scalar::ZERO; layout.size];
+        for (j, digit) in digits.iter().enumerate() {
+            al[j] = Scalar::from(*digit as u64);
+        }
+        let [salt_d, salt_r, salt_s] = [(); 3].map(|_| Scalar::random(&mut rng));
+        let sl: Vec<Scalar> = (0..layout.size).map(|_| Scalar::random(&mut rng)).collect();
+        let sr: Vec<Scalar> = (0..layout.size).map(|_| Scalar::random(&mut rng)).collect();
+        let (commit_v, commit_d, e, reciprocals, commit_r, commit_s) = metrics.measure(ProofPhase::Commitment, || {
+            let commit_v = opening.commit(points);
+            let commit_d = multiscalar_mul(&[(&al, &g_basis), (&multiplicities, &h_basis[layout.digits..layout.digits + layout.base])]) + points.mul_b_i(&salt_d);
+            let e = append_digits(transcript, range, commit_v, commit_d);
+            let reciprocals = inv_vector(&digits.iter().map(|digit| e + Scalar::from(*digit as u64)).collect::<Vec<Scalar>>());
+            let commit_r = inner_product(&reciprocals, &h_basis[..layout.digits]) + points.mul_b_i(&salt_r);
+            let commit_s = multiscalar_mul(&[(&sl, &g_basis), (&sr, &h_basis)]) + points.mul_b_i(&salt_s);
+
+            (commit_v, commit_d, e, reciprocals, commit_r, commit_s)
+        });
+        let [x, y, z] = append_reciprocals(transcript, commit_r, commit_s);
+
+        let mut ar = vec![Scalar::ZERO; layout.size];
+        for (j, reciprocal) in reciprocals.iter().enumerate() {
+            ar[j] = x * reciprocal;
+        }
+        ar[layout.digits..layout.digits + layout.base].copy_from_slice(&multiplicities);
+        let salt_alpha = salt_d + (x * salt_r);
+
+        let [salt_t1, salt_t2] = [Scalar::random(&mut rng), Scalar::random(&mut rng)];
+        let (y_inv, [l0, r0, r1], [commit_t1, commit_t2]) = metrics.measure(ProofPhase::T1T2, || {
+            let cs = Constraints::compute(&layout, e, x);
+            let yn = powers_gen(y, layout.size);
+            let y_inv = powers_gen(invert(&y), layout.size);
+            let l0 = vector_add(&vector_add(&al, &cs.shift), &vec_scalar_mul(&hadamard_multiply(&y_inv, &cs.rho), &(z * z)));
+            let r0 = vector_add(&hadamard_multiply(&yn, &ar), &vec_scalar_mul(&cs.beta, &z));
+            let r1 = hadamard_multiply(&yn, &sr);
+            let t1 = scalar_product(&l0, &r1) + scalar_product(&sl, &r0);
+            let t2 = scalar_product(&sl, &r1);
+
+            (y_inv, [l0, r0, r1], [points.commit(&t1, &salt_t1), points.commit(&t2, &salt_t2)])
+        });
+        let u = append_t1t2(transcript, commit_t1, commit_t2);
+
+        let (lu, ru, [tu, pi_t, pi_lr], y_inv_h) = metrics.measure(ProofPhase::Polynomial, || {
+            let lu = vector_add(&l0, &vec_scalar_mul(&sl, &u));
+            let ru = vector_add(&r0, &vec_scalar_mul(&r1, &u));
+            let tu = scalar_product(&lu, &ru);
+            let pi_t = (salt_t2 * u * u) + (salt_t1 * u) + (z * opening.gamma());
+            let pi_lr = salt_alpha + (salt_s * u);
+
+            (lu, ru, [tu, pi_t, pi_lr], points_hadamard_multiply(&y_inv, &h_basis))
+        });
+        let q = points.mul_g_i(&append_openings(transcript, [tu, pi_t, pi_lr]));
+
+        let ipp = InnerProductProof::create_with_metrics(transcript, [g_basis, y_inv_h], q, [lu, ru], metrics);
+
+        Ok((Self {
+            commit_d,
+            commit_r,
+            commit_s,
+            commit_t1,
+            commit_t2,
+            tu,
+            pi_t,
+            pi_lr,
+            ipp,
+        }, commit_v))
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, range: usize) -> Result<(), ProofError> {
+        self.verify_with_metrics(transcript, points, commit_v, range, &mut Metrics::new())
+    }
+
+    pub fn verify_with_metrics(&self, transcript: &mut Transcript, points: &GlobalPoints, commit_v: RistrettoPoint, range: usize, metrics: &mut Metrics) -> Result<(), ProofError> {
+        let layout = Layout::compute(points, range)?;
+
+        metrics.measure(ProofPhase::Verification, || self.verify_equations(transcript, points, &layout, commit_v, range))
+    }
+
+    fn verify_equations(&self, transcript: &mut Transcript, points: &GlobalPoints, layout: &Layout, commit_v: RistrettoPoint, range: usize) -> Result<(), ProofError> {
+        let g_basis = points.g_basis()[..layout.size].to_vec();
+        let h_basis = points.h_basis()[..layout.size].to_vec();
+
+        let e = append_digits(transcript, range, commit_v, self.commit_d);
+        let [x, y, z] = append_reciprocals(transcript, self.commit_r, self.commit_s);
+        let u = append_t1t2(transcript, self.commit_t1, self.commit_t2);
+        let q = points.mul_g_i(&append_openings(transcript, [self.tu, self.pi_t, self.pi_lr]));
+
+        let cs = Constraints::compute(layout, e, x);
+        let yn = powers_gen(y, layout.size);
+        let y_inv = powers_gen(invert(&y), layout.size);
+        let y_inv_rho = hadamard_multiply(&y_inv, &cs.rho);
+        let delta = scalar_product(&cs.sigma, &yn) + (z * scalar_product(&cs.shift, &cs.beta)) + (z * z * z * scalar_product(&y_inv_rho, &cs.beta));
+
+        let eqn3lhs = points.commit(&self.tu, &self.pi_t);
+        let eqn3rhs = multiscalar_mul(&[(&[z, u, u * u], &[commit_v, self.commit_t1, self.commit_t2])]) + points.mul_g_i(&delta);
+        if eqn3lhs != eqn3rhs {
+            return Err(ProofError::VerificationError);
+        }
+
+        let y_inv_h = points_hadamard_multiply(&y_inv, &h_basis);
+        let commit_p = multiscalar_mul(&[
+            (&[Scalar::ONE, x, u], &[self.commit_d, self.commit_r, self.commit_s]),
+            (&vector_add(&cs.shift, &vec_scalar_mul(&y_inv_rho, &(z * z))), &g_basis),
+            (&vec_scalar_mul(&cs.beta, &z), &y_inv_h),
+        ]) - points.mul_b_i(&self.pi_lr);
+
+        self.ipp.verify(transcript, commit_p + point_mul(&self.tu, &q), [g_basis, y_inv_h], q)
+    }
+
+    // D, R, S, T1, T2, t, pi_t, pi_lr followed by the inner-product proof
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_points(&mut bytes, &[self.commit_d, self.commit_r, self.commit_s, self.commit_t1, self.commit_t2]);
+        write_scalars(&mut bytes, &[self.tu, self.pi_t, self.pi_lr]);
+        bytes.extend(self.ipp.to_bytes());
+
+        bytes
+    }
+
+    // The bit width fixes the vector size and so the number of folding rounds.
+    pub fn from_bytes(bytes: &[u8], range: usize) -> Result<Self, ProofError> {
+        let layout = Layout::for_range(range)?;
+        let mut reader = Reader::new(bytes);
+        let [commit_d, commit_r, commit_s, commit_t1, commit_t2] = [reader.read_point()?, reader.read_point()?, reader.read_point()?, reader.read_point()?, reader.read_point()?];
+        let [tu, pi_t, pi_lr] = [reader.read_scalar()?, reader.read_scalar()?, reader.read_scalar()?];
+        let ipp = InnerProductProof::read(&mut reader, layout.size.trailing_zeros() as usize)?;
+        reader.finish()?;
+
+        Ok(Self {
+            commit_d,
+            commit_r,
+            commit_s,
+            commit_t1,
+            commit_t2,
+            tu,
+            pi_t,
+            pi_lr,
+            ipp,
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str, range: usize) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?, range)
+    }
+
+    pub fn to_armored(&self, range: usize) -> String {
+        armor("ReciprocalRangeProof", range, &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<(Self, usize), ProofError> {
+        let (range, bytes) = dearmor(text, "ReciprocalRangeProof")?;
+
+        Ok((Self::from_bytes(&bytes, range)?, range))
+    }
+
+    pub fn commit_d(&self) -> RistrettoPoint {
+        self.commit_d
+    }
+
+    pub fn commit_r(&self) -> RistrettoPoint {
+        self.commit_r
+    }
+
+    pub fn commit_s(&self) -> RistrettoPoint {
+        self.commit_s
+    }
+
+    pub fn commit_t1(&self) -> RistrettoPoint {
+        self.commit_t1
+    }
+
+    pub fn commit_t2(&self) -> RistrettoPoint {
+        self.commit_t2
+    }
+
+    pub fn tu(&self) -> Scalar {
+        self.tu
+    }
+
+    pub fn pi_t(&self) -> Scalar {
+        self.pi_t
+    }
+
+    pub fn pi_lr(&self) -> Scalar {
+        self.pi_lr
+    }
+
+    pub fn ipp(&self) -> &InnerProductProof {
+        &self.ipp
+    }
+}
+
+// Folding rounds of the inner-product proof for a bit width.
+pub(crate) fn folding_rounds(range: usize) -> Result<usize, ProofError> {
+    Ok(Layout::for_range(range)?.size.trailing_zeros() as usize)
+}
+
+impl Layout {
+    fn compute(points: &GlobalPoints, range: usize) -> Result<Self, ProofError> {
+        check_range(points, range)?;
+        let layout = Layout::for_range(range)?;
+        if points.g_basis().len() < layout.size {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+
+        Ok(layout)
+    }
+
+    // Widths below 4 bits use a single digit in base 2^n.
+    fn for_range(range: usize) -> Result<Self, ProofError> {
+        check_bitsize(range)?;
+        let digit_bits = range.min(DIGIT_BITS);
+        let base = 1 << digit_bits;
+        let digits = range / digit_bits;
+
+        Ok(Self {
+            base,
+            digits,
+            size: (digits + base).next_power_of_two(),
+        })
+    }
+}
+
+impl Constraints {
+    fn compute(layout: &Layout, e: Scalar, x: Scalar) -> Self {
+        let mut shift = vec![Scalar::ZERO; layout.size];
+        let mut sigma = vec![Scalar::ZERO; layout.size];
+        let mut beta = vec![Scalar::ZERO; layout.size];
+        let mut rho = vec![Scalar::ZERO; layout.size];
+        let powers = powers_gen(Scalar::from(layout.base as u64), layout.digits);
+        for j in 0..layout.digits {
+            shift[j] = e;
+            sigma[j] = x;
+            beta[j] = powers[j];
+            rho[j] = Scalar::ONE;
+        }
+        let inverses = inv_vector(&(0..layout.base).map(|i| e + Scalar::from(i as u64)).collect::<Vec<Scalar>>());
+        for (i, inverse) in inverses.iter().enumerate() {
+            rho[layout.digits + i] = -(x * inverse);
+        }
+
+        Self {
+            shift,
+            sigma,
+            beta,
+            rho,
+        }
+    }
+}
+
+fn append_digits(transcript: &mut Transcript, range: usize, commit_v: RistrettoPoint, commit_d: RistrettoPoint) -> Scalar {
+    transcript.append_message(b"dom-sep", b"reciprocal rangeproof v1");
+    transcript.append_u64(b"n", range as u64);
+    transcript.append_point(b"V", &commit_v);
+    transcript.append_point(b"D", &commit_d);
+
+    transcript.challenge_scalar(b"e")
+}
+
+fn append_reciprocals(transcript: &mut Transcript, commit_r: RistrettoPoint, commit_s: RistrettoPoint) -> [Scalar; 3] {
+    transcript.append_point(b"R", &commit_r);
+    transcript.append_point(b"S", &commit_s);
+
+    [transcript.challenge_scalar(b"x"), transcript.challenge_scalar(b"y"), transcript.challenge_scalar(b"z")]
+}
+
+fn append_t1t2(transcript: &mut Transcript, commit_t1: RistrettoPoint, commit_t2: RistrettoPoint) -> Scalar {
+    transcript.append_point(b"T1", &commit_t1);
+    transcript.append_point(b"T2", &commit_t2);
+
+    transcript.challenge_scalar(b"u")
+}
+
+fn append_openings(transcript: &mut Transcript, [tu, pi_t, pi_lr]: [Scalar; 3]) -> Scalar {
+    transcript.append_scalar(b"t_x", &tu);
+    transcript.append_scalar(b"t_x_blinding", &pi_t);
+    transcript.append_scalar(b"e_blinding", &pi_lr);
+
+    transcript.challenge_scalar(b"w")
+}
+
+fn scalar_product(a: &[Scalar], b: &[Scalar]) -> Scalar {
+    a.iter().zip(b).map(|(x1, x2)| x1 * x2).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Digits plus the 16 multiplicities, padded: 32 generators cover 8 and 16 bits.
+    fn prove(value: u64, range: usize) -> (GlobalPoints, ReciprocalRangeProof, RistrettoPoint) {
+        let points = GlobalPoints::gen_global(32);
+        let (proof, commit_v) = ReciprocalRangeProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(value), range).unwrap();
+
+        (points, proof, commit_v)
+    }
+
+    #[test]
+    fn prove_verify_round_trip() {
+        for (value, range) in [(0, 8), (0xff, 8), (0xbeef, 16)] {
+            let (points, proof, commit_v) = prove(value, range);
+
+            assert_eq!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, range), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_commitment_or_transcript() {
+        let (points, proof, commit_v) = prove(200, 8);
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v + points.g_i(), 8).is_err());
+        assert!(proof.verify(&mut Transcript::new(b"other"), &points, commit_v, 8).is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_proof() {
+        let (points, mut proof, commit_v) = prove(200, 8);
+        proof.commit_r += points.g_i();
+
+        assert!(proof.verify(&mut Transcript::new(b"test"), &points, commit_v, 8).is_err());
+    }
+
+    #[test]
+    fn rejects_value_outside_range() {
+        let points = GlobalPoints::gen_global(32);
+        let result = ReciprocalRangeProof::prove(&mut Transcript::new(b"test"), &points, &Opening::random(256), 8);
+
+        assert!(matches!(result, Err(ProofError::ValueOutOfRange)));
+        assert!(matches!(ReciprocalRangeProof::prove(&mut Transcript::new(b"test"), &GlobalPoints::gen_global(16), &Opening::random(1), 8), Err(ProofError::InvalidGeneratorsLength)));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let (points, proof, commit_v) = prove(200, 8);
+        let bytes = proof.to_bytes();
+        let decoded = ReciprocalRangeProof::from_bytes(&bytes, 8).unwrap();
+
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.verify(&mut Transcript::new(b"test"), &points, commit_v, 8), Ok(()));
+        assert_eq!(ReciprocalRangeProof::from_bytes(&bytes, 12).err(), Some(ProofError::InvalidBitsize));
+        assert!(ReciprocalRangeProof::from_bytes(&bytes[..bytes.len() - 1], 8).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+
+use crate::errors::DecodeError;
+
+// Cursor over a proof encoding: points and scalars are 32 bytes each, read in order.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn read_32(&mut self) -> Result<[u8; 32], DecodeError> {
+        if self.bytes.len() < 32 {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (chunk, rest) = self.bytes.split_at(32);
+        self.bytes = rest;
+
+        Ok(chunk.try_into().expect("Chunk is 32 bytes"))
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        let (&byte, rest) = self.bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        self.bytes = rest;
+
+        Ok(byte)
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        let bytes = self.take(8)?.bytes;
+
+        Ok(u64::from_le_bytes(bytes.try_into().expect("Took 8 bytes")))
+    }
+
+    // Splits off the next `len` bytes, for a nested encoding that is decoded on its own.
+    pub(crate) fn take(&mut self, len: usize) -> Result<Reader<'a>, DecodeError> {
+        if self.bytes.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (chunk, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(Reader::new(chunk))
+    }
+
+    // Honest proofs never contain the identity, so it is rejected along with non-canonical encodings.
+    pub(crate) fn read_point(&mut self) -> Result<RistrettoPoint, DecodeError> {
+        let point = CompressedRistretto(self.read_32()?).decompress().ok_or(DecodeError::InvalidPoint)?;
+        if point == RistrettoPoint::identity() {
+            return Err(DecodeError::IdentityPoint);
+        }
+
+        Ok(point)
+    }
+
+    pub(crate) fn read_scalar(&mut self) -> Result<Scalar, DecodeError> {
+        Option::from(Scalar::from_canonical_bytes(self.read_32()?)).ok_or(DecodeError::NonCanonicalScalar)
+    }
+
+    // Number of L, R pairs in front of a fixed tail of `tail` bytes.
+    pub(crate) fn rounds(&self, tail: usize) -> Result<usize, DecodeError> {
+        if self.bytes.len() < tail || !(self.bytes.len() - tail).is_multiple_of(64) {
+            return Err(DecodeError::InvalidLength);
+        }
+
+        Ok((self.bytes.len() - tail) / 64)
+    }
+
+    // Checks that exactly `expected` L, R pairs and the tail are left.
+    pub(crate) fn expect_rounds(&self, tail: usize, expected: usize) -> Result<(), DecodeError> {
+        let expected_len = tail + (expected * 64);
+        match self.rounds(tail) {
+            _ if self.bytes.len() == expected_len => Ok(()),
+            Ok(found) => Err(DecodeError::RoundCountMismatch { expected, found }),
+            Err(_) if self.bytes.len() > expected_len => Err(DecodeError::TrailingBytes),
+            Err(_) => Err(DecodeError::UnexpectedEnd),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<(), DecodeError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+}
+
+pub(crate) fn write_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn write_points(bytes: &mut Vec<u8>, points: &[RistrettoPoint]) {
+    for point in points {
+        bytes.extend_from_slice(point.compress().as_bytes());
+    }
+}
+
+pub(crate) fn write_scalars(bytes: &mut Vec<u8>, scalars: &[Scalar]) {
+    for scalar in scalars {
+        bytes.extend_from_slice(scalar.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+
+    #[test]
+    fn reads_what_was_written() {
+        let mut bytes = vec![];
+        write_points(&mut bytes, &[RISTRETTO_BASEPOINT_POINT]);
+        write_scalars(&mut bytes, &[Scalar::from(7u64)]);
+        let mut reader = Reader::new(&bytes);
+
+        assert_eq!(reader.read_point(), Ok(RISTRETTO_BASEPOINT_POINT));
+        assert_eq!(reader.read_scalar(), Ok(Scalar::from(7u64)));
+        assert_eq!(reader.finish(), Ok(()));
+    }
+
+    #[test]
+    fn reads_integers_and_nested_encodings() {
+        let mut bytes = vec![7];
+        write_u64(&mut bytes, 1 << 40);
+        write_scalars(&mut bytes, &[Scalar::ONE]);
+        let mut reader = Reader::new(&bytes);
+
+        assert_eq!(reader.read_u8(), Ok(7));
+        assert_eq!(reader.read_u64(), Ok(1 << 40));
+        let mut nested = reader.take(32).unwrap();
+        assert_eq!(nested.read_scalar(), Ok(Scalar::ONE));
+        assert_eq!(nested.finish(), Ok(()));
+        assert_eq!(reader.take(1).err(), Some(DecodeError::UnexpectedEnd));
+        assert_eq!(Reader::new(&[0u8; 7]).read_u64(), Err(DecodeError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn rejects_non_canonical_elements() {
+        assert_eq!(Reader::new(&[0u8; 31]).read_point(), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(Reader::new(&[0u8; 32]).read_point(), Err(DecodeError::IdentityPoint));
+        assert_eq!(Reader::new(&[0xffu8; 32]).read_point(), Err(DecodeError::InvalidPoint));
+        assert_eq!(Reader::new(&[0xffu8; 32]).read_scalar(), Err(DecodeError::NonCanonicalScalar));
+        assert_eq!(Reader::new(&[0u8; 1]).finish(), Err(DecodeError::TrailingBytes));
+    }
+
+    #[test]
+    fn checks_round_count() {
+        let bytes = [0u8; 64 * 3 + 64];
+
+        assert_eq!(Reader::new(&bytes).rounds(64), Ok(3));
+        assert_eq!(Reader::new(&bytes).expect_rounds(64, 3), Ok(()));
+        assert_eq!(Reader::new(&bytes).expect_rounds(64, 4), Err(DecodeError::RoundCountMismatch { expected: 4, found: 3 }));
+        assert_eq!(Reader::new(&bytes[1..]).expect_rounds(64, 3), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(Reader::new(&[0u8; 64 * 3 + 65]).expect_rounds(64, 3), Err(DecodeError::TrailingBytes));
+    }
+}
@@ -0,0 +1,278 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+
+use crate::errors::DecodeError;
+use crate::rangeproof::PROTOCOL_VERSION;
+use crate::serialization::Reader;
+
+const ARMOR_BEGIN: &str = "-----BEGIN RANGE PROOF-----";
+const ARMOR_END: &str = "-----END RANGE PROOF-----";
+const ARMOR_WIDTH: usize = 64;
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+// Only lowercase digits, as to_hex writes them, so every byte string has exactly one hex form.
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, DecodeError> {
+    let digit = |x: u8| match x {
+        b'0'..=b'9' => Ok(x - b'0'),
+        b'a'..=b'f' => Ok(x - b'a' + 10),
+        _ => Err(DecodeError::InvalidHex),
+    };
+    if !hex.len().is_multiple_of(2) {
+        return Err(DecodeError::InvalidHex);
+    }
+
+    hex.as_bytes().chunks(2).map(|x| Ok((digit(x[0])? << 4) | digit(x[1])?)).collect()
+}
+
+// Commitments and other points as hex of the compressed encoding, with the same checks as the wire format.
+pub fn point_to_hex(point: &RistrettoPoint) -> String {
+    to_hex(point.compress().as_bytes())
+}
+
+pub fn point_from_hex(hex: &str) -> Result<RistrettoPoint, DecodeError> {
+    let bytes = from_hex(hex)?;
+    let mut reader = Reader::new(&bytes);
+    let point = reader.read_point()?;
+    reader.finish()?;
+
+    Ok(point)
+}
+
+pub fn scalar_to_hex(scalar: &Scalar) -> String {
+    to_hex(scalar.as_bytes())
+}
+
+pub fn scalar_from_hex(hex: &str) -> Result<Scalar, DecodeError> {
+    let bytes = from_hex(hex)?;
+    let mut reader = Reader::new(&bytes);
+    let scalar = reader.read_scalar()?;
+    reader.finish()?;
+
+    Ok(scalar)
+}
+
+// The wire bytes in base64 between BEGIN/END lines. The header names the proof type and version
+// and carries the bit width, which the binary decoders need.
+pub(crate) fn armor(proof: &str, range: usize, bytes: &[u8]) -> String {
+    armor_with(proof, Some(range), bytes)
+}
+
+// For encodings that carry their own sizes, so the header has no Bits line.
+pub(crate) fn armor_plain(proof: &str, bytes: &[u8]) -> String {
+    armor_with(proof, None, bytes)
+}
+
+fn armor_with(proof: &str, range: Option<usize>, bytes: &[u8]) -> String {
+    let body = STANDARD.encode(bytes);
+    let mut text = format!("{}\nProof: {} v{}\n", ARMOR_BEGIN, proof, PROTOCOL_VERSION);
+    if let Some(range) = range {
+        text.push_str(&format!("Bits: {}\n", range));
+    }
+    text.push('\n');
+    for line in body.as_bytes().chunks(ARMOR_WIDTH) {
+        text.push_str(std::str::from_utf8(line).expect("base64 output is ASCII"));
+        text.push('\n');
+    }
+    text.push_str(ARMOR_END);
+    text.push('\n');
+
+    text
+}
+
+// Returns the bit width and wire bytes of an armored proof of the given type.
+pub(crate) fn dearmor(text: &str, proof: &str) -> Result<(usize, Vec<u8>), DecodeError> {
+    match dearmor_with(text, proof, true)? {
+        (Some(range), bytes) => Ok((range, bytes)),
+        (None, _) => Err(DecodeError::InvalidArmor),
+    }
+}
+
+pub(crate) fn dearmor_plain(text: &str, proof: &str) -> Result<Vec<u8>, DecodeError> {
+    Ok(dearmor_with(text, proof, false)?.1)
+}
+
+fn dearmor_with(text: &str, proof: &str, bits: bool) -> Result<(Option<usize>, Vec<u8>), DecodeError> {
+    let mut lines = text.trim().lines().map(str::trim_end);
+    if lines.next() != Some(ARMOR_BEGIN) || lines.next() != Some(format!("Proof: {} v{}", proof, PROTOCOL_VERSION).as_str()) {
+        return Err(DecodeError::InvalidArmor);
+    }
+    let range = match bits {
+        true => Some(lines.next()
+            .and_then(|x| x.strip_prefix("Bits: "))
+            .and_then(|x| x.parse().ok())
+            .ok_or(DecodeError::InvalidArmor)?),
+        false => None,
+    };
+    if lines.next() != Some("") {
+        return Err(DecodeError::InvalidArmor);
+    }
+
+    let mut body = String::new();
+    for line in lines.by_ref() {
+        if line == ARMOR_END {
+            break;
+        }
+        body.push_str(line);
+    }
+    if lines.next().is_some() || !text.trim_end().ends_with(ARMOR_END) {
+        return Err(DecodeError::InvalidArmor);
+    }
+
+    Ok((range, STANDARD.decode(body).map_err(|_| DecodeError::InvalidBase64)?))
+}
+
+// serde helpers, for use as #[serde(with = "..")] on points and scalars.
+#[cfg(feature = "serde")]
+pub mod hex_point {
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(point: &RistrettoPoint, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::point_to_hex(point))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RistrettoPoint, D::Error> {
+        super::point_from_hex(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+pub mod hex_points {
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(points: &[RistrettoPoint], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(points.iter().map(super::point_to_hex))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<RistrettoPoint>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?.iter()
+            .map(|x| super::point_from_hex(x).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+pub mod hex_scalar {
+    use curve25519_dalek::scalar::Scalar;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(scalar: &Scalar, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::scalar_to_hex(scalar))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Scalar, D::Error> {
+        super::scalar_from_hex(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+pub mod hex_point_array {
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use serde::de::Error;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(points: &[RistrettoPoint; N], serializer: S) -> Result<S::Ok, S::Error> {
+        super::hex_points::serialize(points, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[RistrettoPoint; N], D::Error> {
+        super::hex_points::deserialize(deserializer)?.try_into()
+            .map_err(|x: Vec<RistrettoPoint>| D::Error::invalid_length(x.len(), &format!("{} points", N).as_str()))
+    }
+}
+
+#[cfg(feature = "serde")]
+pub mod hex_scalars {
+    use curve25519_dalek::scalar::Scalar;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(scalars: &[Scalar], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(scalars.iter().map(super::scalar_to_hex))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Scalar>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?.iter()
+            .map(|x| super::scalar_from_hex(x).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trip() {
+        let bytes = [0x00, 0x7f, 0xab, 0xff];
+
+        assert_eq!(to_hex(&bytes), "007fabff");
+        assert_eq!(from_hex("007fabff"), Ok(bytes.to_vec()));
+        assert_eq!(from_hex(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn rejects_other_spellings() {
+        for hex in ["007FABFF", "+7", "-7", "0x7f", " 7", "abc", "zz", "é7"] {
+            assert_eq!(from_hex(hex), Err(DecodeError::InvalidHex), "{}", hex);
+        }
+    }
+
+    #[test]
+    fn point_and_scalar_hex_round_trip() {
+        let point = RistrettoPoint::random(&mut rand_core::OsRng);
+        let scalar = Scalar::from(12345u64);
+
+        assert_eq!(point_from_hex(&point_to_hex(&point)), Ok(point));
+        assert_eq!(scalar_from_hex(&scalar_to_hex(&scalar)), Ok(scalar));
+    }
+
+    #[test]
+    fn rejects_non_canonical_elements() {
+        let point = point_to_hex(&RistrettoPoint::random(&mut rand_core::OsRng));
+
+        assert_eq!(point_from_hex(&"00".repeat(32)), Err(DecodeError::IdentityPoint));
+        assert_eq!(point_from_hex(&format!("{}00", point)), Err(DecodeError::TrailingBytes));
+        assert_eq!(point_from_hex(&point[..62]), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(scalar_from_hex(&"ff".repeat(32)), Err(DecodeError::NonCanonicalScalar));
+    }
+
+    #[test]
+    fn armor_round_trip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let text = armor("Test", 8, &bytes);
+
+        assert!(text.lines().all(|x| x.len() <= ARMOR_WIDTH));
+        assert_eq!(dearmor(&text, "Test"), Ok((8, bytes)));
+    }
+
+    #[test]
+    fn plain_armor_has_no_bit_width() {
+        let text = armor_plain("Test", &[1, 2, 3]);
+
+        assert!(!text.contains("Bits:"));
+        assert_eq!(dearmor_plain(&text, "Test"), Ok(vec![1, 2, 3]));
+        assert_eq!(dearmor(&text, "Test"), Err(DecodeError::InvalidArmor));
+        assert_eq!(dearmor_plain(&armor("Test", 8, &[1, 2, 3]), "Test"), Err(DecodeError::InvalidArmor));
+    }
+
+    #[test]
+    fn rejects_malformed_armor() {
+        let text = armor("Test", 8, &[1, 2, 3]);
+
+        assert_eq!(dearmor(&text, "Other"), Err(DecodeError::InvalidArmor));
+        assert_eq!(dearmor(&text.replace("Bits: 8", "Bits: x"), "Test"), Err(DecodeError::InvalidArmor));
+        assert_eq!(dearmor(&text.replace(ARMOR_END, ""), "Test"), Err(DecodeError::InvalidArmor));
+        assert_eq!(dearmor(&format!("{}extra\n", text), "Test"), Err(DecodeError::InvalidArmor));
+        assert_eq!(dearmor(&text.replace("AQID", "AQI*"), "Test"), Err(DecodeError::InvalidBase64));
+    }
+}
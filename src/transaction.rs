@@ -0,0 +1,311 @@
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use rand_core::OsRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DecodeError, ProofError};
+use crate::generator::GlobalPoints;
+use crate::logging::event;
+use crate::prover::Opening;
+use crate::rangeproof::{check_bitsize, RangeProof};
+use crate::serialization::{write_points, write_scalars, write_u64, Reader};
+use crate::text::{armor, dearmor, from_hex, to_hex};
+use crate::transcript::{ProofContext, Transcript};
+#[cfg(feature = "serde")]
+use crate::text::{hex_point, hex_points, hex_scalar};
+
+// Schnorr proof that a commitment is r * b_i, i.e. a commitment to zero.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ZeroProof {
+    #[cfg_attr(feature = "serde", serde(with = "hex_point"))]
+    commit_r: RistrettoPoint,
+    #[cfg_attr(feature = "serde", serde(with = "hex_scalar"))]
+    pi_s: Scalar,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConfidentialTransaction {
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
+    inputs: Vec<RistrettoPoint>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_points"))]
+    outputs: Vec<RistrettoPoint>,
+    fee: u64,
+    range_proofs: Vec<RangeProof>,
+    balance_proof: ZeroProof,
+}
+
+impl ZeroProof {
+    pub fn create(transcript: &mut Transcript, points: &GlobalPoints, blinding: Scalar) -> Self {
+        let k = Scalar::random(&mut OsRng);
+        let commit_r = k * points.b_i();
+        transcript.append_point(b"R", &commit_r);
+        let c = transcript.challenge_scalar(b"c");
+
+        Self {
+            commit_r,
+            pi_s: k + (c * blinding),
+        }
+    }
+
+    pub fn verify(&self, transcript: &mut Transcript, points: &GlobalPoints, commitment: RistrettoPoint) -> Result<(), ProofError> {
+        transcript.append_point(b"R", &self.commit_r);
+        let c = transcript.challenge_scalar(b"c");
+
+        if self.pi_s * points.b_i() == self.commit_r + (c * commitment) {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    pub fn commit_r(&self) -> RistrettoPoint {
+        self.commit_r
+    }
+
+    pub fn pi_s(&self) -> Scalar {
+        self.pi_s
+    }
+}
+
+impl ConfidentialTransaction {
+    pub fn create(context: &ProofContext, inputs: &[Opening], outputs: &[Opening], fee: u64, range: usize, points: &GlobalPoints) -> Result<Self, ProofError> {
+        let total_in: u128 = inputs.iter().map(|x| x.value() as u128).sum();
+        let total_out: u128 = outputs.iter().map(|x| x.value() as u128).sum();
+        if total_in != total_out + fee as u128 {
+            return Err(ProofError::UnbalancedTransaction);
+        }
+
+        let input_commits: Vec<RistrettoPoint> = inputs.iter().map(|x| x.commit(points)).collect();
+        let output_commits: Vec<RistrettoPoint> = outputs.iter().map(|x| x.commit(points)).collect();
+        let mut transcript = ConfidentialTransaction::transcript(context, &input_commits, &output_commits, fee, range);
+
+        let mut range_proofs = vec![];
+        for (output, commit_v) in outputs.iter().zip(output_commits.iter()) {
+            range_proofs.push(RangeProof::prove_opening(&mut transcript, points, output, *commit_v, range)?);
+        }
+
+        let excess_gamma = inputs.iter().map(|x| x.gamma()).sum::<Scalar>() - outputs.iter().map(|x| x.gamma()).sum::<Scalar>();
+        let balance_proof = ZeroProof::create(&mut transcript, points, excess_gamma);
+        event!(outputs = outputs.len(), "confidential transaction ready");
+
+        Ok(Self {
+            inputs: input_commits,
+            outputs: output_commits,
+            fee,
+            range_proofs,
+            balance_proof,
+        })
+    }
+
+    // Checks that inputs - outputs - fee * g_i commits to zero and that every output is in [0, 2^range).
+    pub fn verify(&self, context: &ProofContext, range: usize, points: &GlobalPoints) -> Result<(), ProofError> {
+        if self.range_proofs.len() != self.outputs.len() {
+            return Err(ProofError::VerificationError);
+        }
+        let mut transcript = ConfidentialTransaction::transcript(context, &self.inputs, &self.outputs, self.fee, range);
+
+        for (proof, commit_v) in self.range_proofs.iter().zip(self.outputs.iter()) {
+            proof.verify(&mut transcript, points, *commit_v, range)?;
+        }
+
+        let excess = self.inputs.iter().sum::<RistrettoPoint>() - self.outputs.iter().sum::<RistrettoPoint>()
+            - (Scalar::from(self.fee) * points.g_i());
+        self.balance_proof.verify(&mut transcript, points, excess)?;
+        event!(outputs = self.outputs.len(), "confidential transaction verified");
+
+        Ok(())
+    }
+
+    fn transcript(context: &ProofContext, inputs: &[RistrettoPoint], outputs: &[RistrettoPoint], fee: u64, range: usize) -> Transcript {
+        let mut transcript = context.transcript();
+        transcript.append_message(b"dom-sep", b"confidential transaction v1");
+        transcript.append_u64(b"range", range as u64);
+        transcript.append_u64(b"fee", fee);
+        transcript.append_u64(b"inputs", inputs.len() as u64);
+        for commit in inputs {
+            transcript.append_point(b"V_in", commit);
+        }
+        transcript.append_u64(b"outputs", outputs.len() as u64);
+        for commit in outputs {
+            transcript.append_point(b"V_out", commit);
+        }
+
+        transcript
+    }
+
+    // The fee, the counted inputs and outputs, the balance proof, then one range proof per output.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_u64(&mut bytes, self.fee);
+        write_u64(&mut bytes, self.inputs.len() as u64);
+        write_points(&mut bytes, &self.inputs);
+        write_u64(&mut bytes, self.outputs.len() as u64);
+        write_points(&mut bytes, &self.outputs);
+        write_points(&mut bytes, &[self.balance_proof.commit_r]);
+        write_scalars(&mut bytes, &[self.balance_proof.pi_s]);
+        for proof in self.range_proofs.iter() {
+            bytes.extend(proof.to_bytes());
+        }
+
+        bytes
+    }
+
+    // As with RangeProof::from_bytes, the bit width fixes the size of each range proof.
+    pub fn from_bytes(bytes: &[u8], range: usize) -> Result<Self, ProofError> {
+        check_bitsize(range)?;
+        let mut reader = Reader::new(bytes);
+        let fee = reader.read_u64()?;
+        let input_count = reader.read_u64()?;
+        let inputs = (0..input_count).map(|_| reader.read_point()).collect::<Result<Vec<RistrettoPoint>, DecodeError>>()?;
+        let output_count = reader.read_u64()?;
+        let outputs = (0..output_count).map(|_| reader.read_point()).collect::<Result<Vec<RistrettoPoint>, DecodeError>>()?;
+        let balance_proof = ZeroProof {
+            commit_r: reader.read_point()?,
+            pi_s: reader.read_scalar()?,
+        };
+        let range_proofs = (0..output_count).map(|_| RangeProof::read(&mut reader, range)).collect::<Result<Vec<RangeProof>, ProofError>>()?;
+        reader.finish()?;
+
+        Ok(Self {
+            inputs,
+            outputs,
+            fee,
+            range_proofs,
+            balance_proof,
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str, range: usize) -> Result<Self, ProofError> {
+        Self::from_bytes(&from_hex(hex)?, range)
+    }
+
+    pub fn to_armored(&self, range: usize) -> String {
+        armor("ConfidentialTransaction", range, &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<(Self, usize), ProofError> {
+        let (range, bytes) = dearmor(text, "ConfidentialTransaction")?;
+
+        Ok((Self::from_bytes(&bytes, range)?, range))
+    }
+
+    pub fn inputs(&self) -> Vec<RistrettoPoint> {
+        self.inputs.clone()
+    }
+
+    pub fn outputs(&self) -> Vec<RistrettoPoint> {
+        self.outputs.clone()
+    }
+
+    pub fn fee(&self) -> u64 {
+        self.fee
+    }
+
+    pub fn range_proofs(&self) -> &[RangeProof] {
+        &self.range_proofs
+    }
+
+    pub fn balance_proof(&self) -> &ZeroProof {
+        &self.balance_proof
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(context: &ProofContext, points: &GlobalPoints) -> ConfidentialTransaction {
+        let inputs = [Opening::random(70), Opening::random(30)];
+        let outputs = [Opening::random(60), Opening::random(35)];
+
+        ConfidentialTransaction::create(context, &inputs, &outputs, 5, 8, points).unwrap()
+    }
+
+    #[test]
+    fn balanced_transaction_verifies() {
+        let points = GlobalPoints::gen_global(8);
+        let context = ProofContext::new(b"ledger", b"tx 1");
+
+        assert_eq!(transaction(&context, &points).verify(&context, 8, &points), Ok(()));
+    }
+
+    #[test]
+    fn rejects_unbalanced_transaction() {
+        let points = GlobalPoints::gen_global(8);
+        let context = ProofContext::new(b"ledger", b"tx 1");
+        let result = ConfidentialTransaction::create(&context, &[Opening::random(10)], &[Opening::random(9)], 0, 8, &points);
+
+        assert!(matches!(result, Err(ProofError::UnbalancedTransaction)));
+    }
+
+    #[test]
+    fn rejects_changed_fee() {
+        let points = GlobalPoints::gen_global(8);
+        let context = ProofContext::new(b"ledger", b"tx 1");
+        let mut tx = transaction(&context, &points);
+        tx.fee += 1;
+
+        assert!(tx.verify(&context, 8, &points).is_err());
+    }
+
+    #[test]
+    fn rejects_other_context() {
+        let points = GlobalPoints::gen_global(8);
+        let tx = transaction(&ProofContext::new(b"ledger", b"tx 1"), &points);
+
+        assert!(tx.verify(&ProofContext::new(b"ledger", b"tx 2"), 8, &points).is_err());
+    }
+
+    #[test]
+    fn rejects_swapped_output() {
+        let points = GlobalPoints::gen_global(8);
+        let context = ProofContext::new(b"ledger", b"tx 1");
+        let mut tx = transaction(&context, &points);
+        tx.outputs[0] = Opening::random(60).commit(&points);
+
+        assert!(tx.verify(&context, 8, &points).is_err());
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let points = GlobalPoints::gen_global(8);
+        let context = ProofContext::new(b"ledger", b"tx 1");
+        let tx = transaction(&context, &points);
+        let from_bytes = ConfidentialTransaction::from_bytes(&tx.to_bytes(), 8).unwrap();
+        let from_hex = ConfidentialTransaction::from_hex(&tx.to_hex(), 8).unwrap();
+        let (from_armor, range) = ConfidentialTransaction::from_armored(&tx.to_armored(8)).unwrap();
+
+        assert_eq!(from_bytes.to_bytes(), tx.to_bytes());
+        assert_eq!(from_hex.to_bytes(), tx.to_bytes());
+        assert_eq!(from_armor.verify(&context, range, &points), Ok(()));
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        let points = GlobalPoints::gen_global(8);
+        let tx = transaction(&ProofContext::new(b"ledger", b"tx 1"), &points);
+        let bytes = tx.to_bytes();
+
+        assert!(ConfidentialTransaction::from_bytes(&bytes, 16).is_err());
+        assert_eq!(ConfidentialTransaction::from_bytes(&bytes, 7).err(), Some(ProofError::InvalidBitsize));
+        assert_eq!(ConfidentialTransaction::from_bytes(&bytes[..bytes.len() - 1], 8).err(), Some(DecodeError::UnexpectedEnd.into()));
+        assert_eq!(ConfidentialTransaction::from_bytes(&[&bytes[..], &[0]].concat(), 8).err(), Some(DecodeError::TrailingBytes.into()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_through_the_bytes() {
+        let points = GlobalPoints::gen_global(8);
+        let context = ProofContext::new(b"ledger", b"tx 1");
+        let tx = transaction(&context, &points);
+        let decoded: ConfidentialTransaction = serde_json::from_str(&serde_json::to_string(&tx).unwrap()).unwrap();
+
+        assert_eq!(decoded.to_bytes(), tx.to_bytes());
+        assert_eq!(decoded.verify(&context, 8, &points), Ok(()));
+    }
+}
@@ -0,0 +1,172 @@
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use sha2::{Sha512, Digest};
+
+// Fiat-Shamir transcript: every message is absorbed into a running Sha512 state,
+// challenges are squeezed from a copy of that state and absorbed back in.
+#[derive(Clone)]
+pub struct Transcript {
+    state: Sha512,
+}
+
+// Application binding for a proof: the label names the protocol or application, the context
+// identifies what the proof is attached to (a transaction, a session) and the nonce makes each
+// proof single use. All of it is absorbed before any challenge, so a proof made under one
+// context fails verification under another.
+//
+// ConfidentialTransaction takes a context directly. Every other prover and verifier takes a
+// Transcript, and transcript() is the way to bind them: start both sides from
+// context.transcript() instead of Transcript::new.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofContext {
+    label: Vec<u8>,
+    context: Vec<u8>,
+    nonce: Option<Vec<u8>>,
+}
+
+impl Transcript {
+    pub fn new(label: &[u8]) -> Self {
+        let mut transcript = Self {
+            state: Sha512::new(),
+        };
+        transcript.append_message(b"dom-sep", label);
+
+        transcript
+    }
+
+    pub fn append_message(&mut self, label: &[u8], message: &[u8]) {
+        self.state.update((label.len() as u64).to_le_bytes());
+        self.state.update(label);
+        self.state.update((message.len() as u64).to_le_bytes());
+        self.state.update(message);
+    }
+
+    pub fn append_u64(&mut self, label: &[u8], value: u64) {
+        self.append_message(label, &value.to_le_bytes());
+    }
+
+    pub fn append_scalar(&mut self, label: &[u8], scalar: &Scalar) {
+        self.append_message(label, scalar.as_bytes());
+    }
+
+    pub fn append_point(&mut self, label: &[u8], point: &RistrettoPoint) {
+        self.append_message(label, point.compress().as_bytes());
+    }
+
+    pub fn challenge_scalar(&mut self, label: &[u8]) -> Scalar {
+        let mut hasher = self.state.clone();
+        hasher.update(b"challenge");
+        hasher.update((label.len() as u64).to_le_bytes());
+        hasher.update(label);
+        let challenge = Scalar::from_bytes_mod_order_wide(&hasher.finalize().into());
+        self.append_scalar(label, &challenge);
+
+        challenge
+    }
+}
+
+impl ProofContext {
+    pub fn new(label: &[u8], context: &[u8]) -> Self {
+        Self {
+            label: label.to_vec(),
+            context: context.to_vec(),
+            nonce: None,
+        }
+    }
+
+    pub fn with_nonce(mut self, nonce: &[u8]) -> Self {
+        self.nonce = Some(nonce.to_vec());
+
+        self
+    }
+
+    pub fn transcript(&self) -> Transcript {
+        let mut transcript = Transcript::new(&self.label);
+        transcript.append_message(b"context", &self.context);
+        match &self.nonce {
+            Some(nonce) => {
+                transcript.append_u64(b"has_nonce", 1);
+                transcript.append_message(b"nonce", nonce);
+            }
+            None => transcript.append_u64(b"has_nonce", 0),
+        }
+
+        transcript
+    }
+
+    pub fn label(&self) -> &[u8] {
+        &self.label
+    }
+
+    pub fn context(&self) -> &[u8] {
+        &self.context
+    }
+
+    pub fn nonce(&self) -> Option<&[u8]> {
+        self.nonce.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equality::{CommitmentBases, EqualityProof};
+    use crate::generator::GlobalPoints;
+    use crate::prover::{Opening, Salts};
+    use crate::rangeproof::RangeProof;
+
+    #[test]
+    fn challenges_depend_on_every_message() {
+        let challenge = |label: &[u8], message: &[u8]| {
+            let mut transcript = Transcript::new(label);
+            transcript.append_message(b"m", message);
+            transcript.challenge_scalar(b"c")
+        };
+
+        assert_eq!(challenge(b"a", b"x"), challenge(b"a", b"x"));
+        assert_ne!(challenge(b"a", b"x"), challenge(b"b", b"x"));
+        assert_ne!(challenge(b"a", b"x"), challenge(b"a", b"y"));
+    }
+
+    #[test]
+    fn challenge_label_is_length_prefixed() {
+        let transcript = Transcript::new(b"a");
+        let mut hasher = transcript.state.clone();
+        hasher.update(b"challenge");
+        hasher.update(1u64.to_le_bytes());
+        hasher.update(b"c");
+
+        assert_eq!(transcript.clone().challenge_scalar(b"c"), Scalar::from_bytes_mod_order_wide(&hasher.finalize().into()));
+    }
+
+    #[test]
+    fn range_proof_is_bound_to_its_context() {
+        let points = GlobalPoints::gen_global(8);
+        let context = ProofContext::new(b"app", b"session 1").with_nonce(b"n1");
+        let (proof, commit_v) = RangeProof::prove(&mut context.transcript(), &points, 42, 8, Salts::init()).unwrap();
+
+        assert_eq!(proof.verify(&mut context.transcript(), &points, commit_v, 8), Ok(()));
+        assert!(proof.verify(&mut ProofContext::new(b"app", b"session 2").with_nonce(b"n1").transcript(), &points, commit_v, 8).is_err());
+        assert!(proof.verify(&mut ProofContext::new(b"app", b"session 1").transcript(), &points, commit_v, 8).is_err());
+    }
+
+    #[test]
+    fn equality_proof_is_bound_to_its_context() {
+        let bases = [CommitmentBases::from_points(&GlobalPoints::gen_global(8)); 2];
+        let context = ProofContext::new(b"app", b"session 1");
+        let (proof, commits) = EqualityProof::prove(&mut context.transcript(), bases, [&Opening::random(7), &Opening::random(7)]).unwrap();
+
+        assert_eq!(proof.verify(&mut context.transcript(), bases, commits), Ok(()));
+        assert!(proof.verify(&mut ProofContext::new(b"other app", b"session 1").transcript(), bases, commits).is_err());
+    }
+
+    #[test]
+    fn accessors_return_the_parts() {
+        let context = ProofContext::new(b"app", b"session 1").with_nonce(b"n1");
+
+        assert_eq!(context.label(), b"app");
+        assert_eq!(context.context(), b"session 1");
+        assert_eq!(context.nonce(), Some(&b"n1"[..]));
+        assert_eq!(ProofContext::new(b"app", b"").nonce(), None);
+    }
+}
@@ -1,21 +1,41 @@
+use std::fmt;
+
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::ristretto::RistrettoPoint;
-use rand::Rng;
 use rand_core::OsRng;
 
+use crate::aggregation::check_widths;
 use crate::bullerproof::fold_vector;
-use crate::generator::{n2_gen, gen_scalars, GlobalPoints};
-use crate::operations::{hadamard_multiply, inner_product, inv_vector, points_hadamard_multiply, scalarize, vec_scalar_mul, vector_add};
+use crate::errors::ProofError;
+use crate::generator::{n2_gen, powers_gen, FixedBase, GlobalPoints};
+use crate::logging::{event, Phase};
+use crate::operations::{hadamard_multiply, inner_product, invert, point_mul, point_sum, points_hadamard_multiply, vec_scalar_mul, vector_add};
 use crate::prover::{Polycommitment, T1T2commitment};
+use crate::text::to_hex;
 
 pub struct Generatives {
-    y: u64,
-    z: u64,
+    y: Scalar,
+    z: Scalar,
     u: Scalar,
     yn: Vec<Scalar>,
     y_inv_h: Vec<RistrettoPoint>,
 }
 
+// What the verification equations need that depends only on the bit widths, the party count and
+// the generators: the powers of two and their sums, the bases cut to the proof length, and the
+// sum of the G basis for the <-z, G> term. Build one per shape and reuse it across proofs.
+#[derive(Clone)]
+pub struct VerifierKey {
+    ranges: Vec<usize>,
+    n2: Vec<Scalar>,
+    n2_sums: Vec<Scalar>,
+    g_basis: Vec<RistrettoPoint>,
+    h_basis: Vec<RistrettoPoint>,
+    g_sum: RistrettoPoint,
+    g_i: FixedBase,
+    b_i: FixedBase,
+}
+
 pub struct LinearVerify {
     lu: Vec<Scalar>,
     ru: Vec<Scalar>,
@@ -26,6 +46,30 @@ pub struct LinearVerify {
     eqn3rhs: RistrettoPoint,
 }
 
+// The equations checked by BulletVerify and LinearVerify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equation {
+    InnerProduct,
+    CommitmentAS,
+    CommitmentVT,
+    PolynomialT,
+}
+
+// One evaluated equation. Points are stored compressed and scalars as their canonical bytes,
+// so a report from one implementation can be compared byte for byte with another's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquationCheck {
+    equation: Equation,
+    passed: bool,
+    lhs: [u8; 32],
+    rhs: [u8; 32],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    checks: Vec<EquationCheck>,
+}
+
 pub struct BulletVerify {
     commit_a: RistrettoPoint,
     commit_s: RistrettoPoint,
@@ -45,15 +89,19 @@ pub struct BulletVerify {
 }
 
 impl BulletVerify {
-    pub fn init([left, right]: [RistrettoPoint; 2], asv: [RistrettoPoint; 3], [commit_t1, commit_t2]: [RistrettoPoint; 2], data: (RistrettoPoint, [Scalar; 3]), points: &GlobalPoints, y_inv_h: Vec<RistrettoPoint>, z: u64, count: usize) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn init([left, right]: [RistrettoPoint; 2], asv: [RistrettoPoint; 3], [commit_t1, commit_t2]: [RistrettoPoint; 2], data: (RistrettoPoint, [Scalar; 3]), points: &GlobalPoints, y_inv_h: &[RistrettoPoint], z: Scalar, _count: usize) -> Self {
         let u_random = Scalar::random(&mut OsRng);
         let commit_c = data.0;
         let [tu, pi_lr, pi_t] = data.1;
         let mut commit_p = commit_c + (tu * points.g_i());
-        commit_p = (left * u_random * u_random) + (right * u_random.invert() * u_random.invert()) + commit_p;
-        let g_basis_fold = fold_vector(&mut points.g_basis().clone(), u_random.invert());
-        let h_basis_fold = fold_vector(&mut y_inv_h.clone(), u_random);
-        let z = Scalar::from(z);
+        let u_inv = u_random.invert();
+        commit_p = (left * u_random * u_random) + (right * u_inv * u_inv) + commit_p;
+        let mut g_basis_fold = points.g_basis()[..y_inv_h.len()].to_vec();
+        let mut h_basis_fold = y_inv_h.to_vec();
+        fold_vector(&mut g_basis_fold, u_inv, u_random);
+        fold_vector(&mut h_basis_fold, u_random, u_inv);
+
         Self {
             commit_a: asv[0],
             commit_s: asv[1],
@@ -73,76 +121,102 @@ impl BulletVerify {
         }
     }
 
-    pub fn compute(&mut self, [left, right]: [RistrettoPoint; 2]) -> () {
-        self.commit_p = (left * self.u_random * self.u_random) + (right * self.u_random.invert() * self.u_random.invert()) + self.commit_p;
-        self.g_basis_fold = fold_vector(&mut self.g_basis_fold.clone(), self.u_random.invert());
-        self.h_basis_fold = fold_vector(&mut self.h_basis_fold.clone(), self.u_random);
+    pub fn compute(&mut self, [left, right]: [RistrettoPoint; 2]) {
+        let u_inv = self.u_random.invert();
+        self.commit_p = (left * self.u_random * self.u_random) + (right * u_inv * u_inv) + self.commit_p;
+        fold_vector(&mut self.g_basis_fold, u_inv, self.u_random);
+        fold_vector(&mut self.h_basis_fold, self.u_random, u_inv);
     }
 
-    pub fn u_gen(&mut self) -> () {
+    pub fn u_gen(&mut self) {
         self.u_random = Scalar::random(&mut OsRng);
     }
 
     pub fn u_random(&mut self) -> Scalar {
-        self.u_random.clone()
+        self.u_random
     }
 
-    pub fn h_basis(&self) -> Vec<RistrettoPoint> {
-        self.h_basis_fold.clone()
+    pub fn h_basis(&self) -> &[RistrettoPoint] {
+        &self.h_basis_fold
     }
 
-    pub fn g_basis(&self) -> Vec<RistrettoPoint> {
-        self.g_basis_fold.clone()
+    pub fn g_basis(&self) -> &[RistrettoPoint] {
+        &self.g_basis_fold
     }
 
     pub fn commit_p(&self) -> RistrettoPoint {
         self.commit_p
     }
 
-    pub fn verify(&mut self, [a, b]: [Vec<Scalar>; 2], count: usize, points: &GlobalPoints, gen: &Generatives) -> () {
-        let n2 = n2_gen(count);
-        let z2 = Scalar::from(gen.z()) * Scalar::from(gen.z());
-        let delta: Scalar = ((Scalar::from(gen.z()) - z2) * gen.yn().iter().sum::<Scalar>()) 
-            - (z2 * Scalar::from(gen.z()) * n2.iter().sum::<Scalar>());
+    pub fn verify(&mut self, [a, b]: [&[Scalar]; 2], count: usize, points: &GlobalPoints, gen: &Generatives) -> Result<(), ProofError> {
+        let key = VerifierKey::new(count, points)?;
+
+        self.verify_with_key([a, b], &key, gen)
+    }
+
+    // Fails with VerificationError when any equation does not hold; report_with_key says which.
+    pub fn verify_with_key(&mut self, [a, b]: [&[Scalar]; 2], key: &VerifierKey, gen: &Generatives) -> Result<(), ProofError> {
+        let _phase = Phase::enter("bulletproof verification", key.range());
+        let report = self.report_with_key([a, b], key, gen);
+        event!(passed = report.passed(), "bulletproof verification finished");
+
+        report.result()
+    }
+
+    // Evaluates every equation instead of stopping at the first failure.
+    pub fn report(&self, [a, b]: [&[Scalar]; 2], count: usize, points: &GlobalPoints, gen: &Generatives) -> Result<VerificationReport, ProofError> {
+        let key = VerifierKey::new(count, points)?;
+
+        Ok(self.report_with_key([a, b], &key, gen))
+    }
+
+    pub fn report_with_key(&self, [a, b]: [&[Scalar]; 2], key: &VerifierKey, gen: &Generatives) -> VerificationReport {
+        let z2 = self.z * self.z;
+        let delta = key.delta(gen.yn.iter().sum(), self.z);
         let eqn1lhs = self.commit_p;
         let eqn1rhs = (a[0] * self.g_basis_fold[0]) + (b[0] * self.h_basis_fold[0]) + (a[0] * b[0] * self.g_i);
-        let eqn2lhs = self.commit_a + (self.commit_s * gen.u()) + inner_product(&vec![Scalar::from(0u64) - self.z; count], &points.g_basis())
-            + inner_product(&vector_add(&vec_scalar_mul(&gen.yn(), &Scalar::from(self.z)), &vec_scalar_mul(&n2, &z2)), 
-            &gen.y_inv_h());
-        let eqn2rhs = self.commit_c + (self.pi_lr * points.b_i());
-        let eqn3lhs = (self.tu * points.g_i()) + (self.pi_t * points.b_i());
-        let eqn3rhs = (self.commit_v * z2) + (delta * points.g_i()) + (self.commit_t1 * gen.u()) + (self.commit_t2 * gen.u() * gen.u());
+        let eqn2lhs = self.commit_a + (self.commit_s * gen.u) + key.g_term(self.z)
+            + inner_product(&vector_add(&vec_scalar_mul(&gen.yn, &self.z), &vec_scalar_mul(&key.n2, &z2)), &gen.y_inv_h);
+        let eqn2rhs = self.commit_c + key.mul_b_i(&self.pi_lr);
+        let eqn3lhs = key.mul_g_i(&self.tu) + key.mul_b_i(&self.pi_t);
+        let eqn3rhs = (self.commit_v * z2) + key.mul_g_i(&delta) + (self.commit_t1 * gen.u) + (self.commit_t2 * gen.u * gen.u);
 
-        println!("Bulletproof Verifying...");
-        assert_eq!(eqn1lhs, eqn1rhs, "Final, P != aG + bH + ab(G_i)");
-        assert_eq!(eqn2lhs, eqn2rhs, "Final, A, S verification failed");
-        assert_eq!(eqn3lhs, eqn3rhs, "Final, V, T1, T2 verification failed");
-        println!("Verified successfully")
+        VerificationReport {
+            checks: vec![
+                EquationCheck::points(Equation::InnerProduct, eqn1lhs, eqn1rhs),
+                EquationCheck::points(Equation::CommitmentAS, eqn2lhs, eqn2rhs),
+                EquationCheck::points(Equation::CommitmentVT, eqn3lhs, eqn3rhs),
+            ],
+        }
     }
 }
 
 impl LinearVerify {
-    pub fn init_linear(count: usize, points: &GlobalPoints, prover: &Polycommitment, asv: [RistrettoPoint; 3], gen: &Generatives, t_commit: &T1T2commitment) -> Self {
+    pub fn init_linear(count: usize, points: &GlobalPoints, prover: &Polycommitment, asv: [RistrettoPoint; 3], gen: &Generatives, t_commit: &T1T2commitment) -> Result<Self, ProofError> {
+        let key = VerifierKey::new(count, points)?;
+
+        Ok(LinearVerify::init_with_key(&key, prover, asv, gen, t_commit))
+    }
+
+    pub fn init_with_key(key: &VerifierKey, prover: &Polycommitment, asv: [RistrettoPoint; 3], gen: &Generatives, t_commit: &T1T2commitment) -> Self {
+        let _phase = Phase::enter("linear verification setup", key.range());
         let [commit_a, commit_s, commit_v] = asv;
-        let n2 = n2_gen(count);
-        let z2 = Scalar::from(gen.z()) * Scalar::from(gen.z());
-        let delta: Scalar = ((Scalar::from(gen.z()) - z2) * gen.yn().iter().sum::<Scalar>()) 
-            - ((z2 * Scalar::from(gen.z())) * n2.iter().sum::<Scalar>());
-
-        let eqn2lhs = commit_a + (commit_s * gen.u()) + inner_product(&scalarize(&mut vec![-1 * gen.z() as i64; count]), &points.g_basis())
-            + inner_product(&vector_add(&vec_scalar_mul(&gen.yn(), &Scalar::from(gen.z())), &vec_scalar_mul(&n2, &z2)), 
-            &gen.y_inv_h());
-        let eqn2rhs = inner_product(&prover.lu(), &points.g_basis()) + inner_product(&prover.ru(), &gen.y_inv_h()) 
-            + (prover.pi_lr() * points.b_i());
+        let z = gen.z;
+        let z2 = z * z;
+        let delta = key.delta(gen.yn.iter().sum(), z);
+
+        let eqn2lhs = commit_a + (commit_s * gen.u) + key.g_term(z)
+            + inner_product(&vector_add(&vec_scalar_mul(&gen.yn, &z), &vec_scalar_mul(&key.n2, &z2)), &gen.y_inv_h);
+        let eqn2rhs = inner_product(prover.lu(), &key.g_basis) + inner_product(prover.ru(), &gen.y_inv_h) 
+            + key.mul_b_i(&prover.pi_lr());
         
-        let eqn3lhs = (prover.tu() * points.g_i()) + (prover.pi_t() * points.b_i());
-        let eqn3rhs = (commit_v * z2) + (delta * points.g_i()) + (t_commit.commit_t1() * gen.u()) + (t_commit.commit_t2() * gen.u() * gen.u());
+        let eqn3lhs = key.mul_g_i(&prover.tu()) + key.mul_b_i(&prover.pi_t());
+        let eqn3rhs = (commit_v * z2) + key.mul_g_i(&delta) + (t_commit.commit_t1() * gen.u) + (t_commit.commit_t2() * gen.u * gen.u);
 
-        println!("Linear Verification setup initialized");
 
         Self {
-            lu: prover.lu(),
-            ru: prover.ru(),
+            lu: prover.lu().to_vec(),
+            ru: prover.ru().to_vec(),
             tu: prover.tu(),
             eqn2lhs,
             eqn2rhs,
@@ -155,24 +229,47 @@ impl LinearVerify {
         Scalar::random(&mut OsRng)
     }
 
-    pub fn verify(&self) -> () {
-        println!("Linearly Verifying...");
-        assert_eq!(hadamard_multiply(&self.lu, &self.ru).iter().sum::<Scalar>(), self.tu, "tu != <lu, ru>");
-        assert_eq!(self.eqn2lhs, self.eqn2rhs, "Final, A, S verification failed");
-        assert_eq!(self.eqn3lhs, self.eqn3rhs, "Final, V, T1, T2 verification failed");
-        println!("Verified successfully")
+    pub fn verify(&self) -> Result<(), ProofError> {
+        let _phase = Phase::enter("linear verification", self.lu.len());
+        let report = self.report();
+        event!(passed = report.passed(), "linear verification finished");
+
+        report.result()
+    }
+
+    pub fn report(&self) -> VerificationReport {
+        VerificationReport {
+            checks: vec![
+                EquationCheck::scalars(Equation::PolynomialT, self.tu, hadamard_multiply(&self.lu, &self.ru).iter().sum::<Scalar>()),
+                EquationCheck::points(Equation::CommitmentAS, self.eqn2lhs, self.eqn2rhs),
+                EquationCheck::points(Equation::CommitmentVT, self.eqn3lhs, self.eqn3rhs),
+            ],
+        }
     }
 }
 
 impl Generatives {
     pub fn init(count: usize, points: &GlobalPoints) -> Self {
-        let y = rand::thread_rng().gen();
-        let z = rand::thread_rng().gen();
+        let y = Scalar::random(&mut OsRng);
+        let z = Scalar::random(&mut OsRng);
         let u = Scalar::random(&mut OsRng);
-        let yn = gen_scalars(count, y);
-        let yinv = inv_vector(&yn);
-        let y_inv_h = points_hadamard_multiply(&yinv, &points.h_basis());
-        println!("Generated y, z, y_inv_H");
+
+        Generatives::from_challenges([y, z], u, count, points)
+    }
+
+    pub fn from_challenges([y, z]: [Scalar; 2], u: Scalar, count: usize, points: &GlobalPoints) -> Self {
+        Generatives::with_basis([y, z], u, count, points.h_basis())
+    }
+
+    pub fn from_key([y, z]: [Scalar; 2], u: Scalar, key: &VerifierKey) -> Self {
+        Generatives::with_basis([y, z], u, key.h_basis.len(), &key.h_basis)
+    }
+
+    fn with_basis([y, z]: [Scalar; 2], u: Scalar, count: usize, h_basis: &[RistrettoPoint]) -> Self {
+        let _phase = Phase::enter("verifier challenges", count);
+        let yn = powers_gen(y, count);
+        let y_inv_h = points_hadamard_multiply(&powers_gen(invert(&y), count), h_basis);
+        event!("generated y, z, y_inv_H");
 
         Self {
             y,
@@ -183,7 +280,7 @@ impl Generatives {
         }
     }
 
-    pub fn to_prover_yz(&self) -> [u64; 2] {
+    pub fn to_prover_yz(&self) -> [Scalar; 2] {
         [self.y, self.z]
     }
 
@@ -191,20 +288,341 @@ impl Generatives {
         self.u
     }
 
-    pub fn z(&self) -> u64 {
+    pub fn z(&self) -> Scalar {
         self.z
     }
 
-    pub fn y(&self) -> u64 {
+    pub fn y(&self) -> Scalar {
         self.y
     }
 
-    pub fn yn(&self) -> Vec<Scalar> {
-        self.yn.clone()
-    } 
+    pub fn yn(&self) -> &[Scalar] {
+        &self.yn
+    }
+
+    pub fn y_inv_h(&self) -> &[RistrettoPoint] {
+        &self.y_inv_h
+    }
+}
+
+impl VerifierKey {
+    // For a single value of `range` bits, padded to a power of two.
+    pub fn new(range: usize, points: &GlobalPoints) -> Result<Self, ProofError> {
+        if range == 0 || range > 64 {
+            return Err(ProofError::InvalidBitsize);
+        }
+
+        VerifierKey::aggregated(&[range], points)
+    }
+
+    // For an AggregatedRangeProof over one value per entry of `ranges`, the bits laid out back to
+    // back and padded to a power of two.
+    pub fn aggregated(ranges: &[usize], points: &GlobalPoints) -> Result<Self, ProofError> {
+        let count = check_widths(ranges)?;
+        if points.g_basis().len() < count {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        let _phase = Phase::enter("verifier key", count);
+        let segments: Vec<Vec<Scalar>> = ranges.iter().map(|&n| n2_gen(n)).collect();
+        let n2_sums = segments.iter().map(|n2| n2.iter().sum()).collect();
+        let g_basis = points.g_basis()[..count].to_vec();
+        let g_sum = point_sum(&g_basis);
+
+        Ok(Self {
+            ranges: ranges.to_vec(),
+            n2: segments.concat(),
+            n2_sums,
+            g_basis,
+            h_basis: points.h_basis()[..count].to_vec(),
+            g_sum,
+            g_i: points.fixed_g_i(),
+            b_i: points.fixed_b_i(),
+        })
+    }
+
+    // delta(y, z) = (z - z^2) * <1, y^n> - sum_j z^(3+j) * <1, 2^n_j>, from the sum of the y powers.
+    // With one value this is the single proof's (z - z^2) * <1, y^n> - z^3 * <1, 2^n>.
+    pub fn delta(&self, yn_sum: Scalar, z: Scalar) -> Scalar {
+        let mut zj = z * z * z;
+        let mut segments = Scalar::ZERO;
+        for n2_sum in self.n2_sums.iter() {
+            segments += zj * n2_sum;
+            zj *= z;
+        }
+
+        ((z - (z * z)) * yn_sum) - segments
+    }
+
+    // <-z^n, G> as a single multiplication of the cached basis sum.
+    pub fn g_term(&self, z: Scalar) -> RistrettoPoint {
+        point_mul(&-z, &self.g_sum)
+    }
+
+    // The total bit width, which for a single-value key is its width.
+    pub fn range(&self) -> usize {
+        self.ranges.iter().sum()
+    }
+
+    pub fn ranges(&self) -> &[usize] {
+        &self.ranges
+    }
+
+    pub fn parties(&self) -> usize {
+        self.ranges.len()
+    }
+
+    // The powers of two of each value, back to back.
+    pub fn n2(&self) -> &[Scalar] {
+        &self.n2
+    }
+
+    pub fn n2_sum(&self) -> Scalar {
+        self.n2_sums.iter().sum()
+    }
+
+    pub fn n2_sums(&self) -> &[Scalar] {
+        &self.n2_sums
+    }
+
+    pub fn g_basis(&self) -> &[RistrettoPoint] {
+        &self.g_basis
+    }
+
+    pub fn h_basis(&self) -> &[RistrettoPoint] {
+        &self.h_basis
+    }
+
+    // Multiplications by G and B use the generator tables when the key was built from points with tables.
+    pub fn mul_g_i(&self, scalar: &Scalar) -> RistrettoPoint {
+        self.g_i.mul(scalar)
+    }
+
+    pub fn mul_b_i(&self, scalar: &Scalar) -> RistrettoPoint {
+        self.b_i.mul(scalar)
+    }
+
+    pub fn g_i(&self) -> RistrettoPoint {
+        self.g_i.point()
+    }
+
+    pub fn b_i(&self) -> RistrettoPoint {
+        self.b_i.point()
+    }
+}
+
+impl EquationCheck {
+    fn points(equation: Equation, lhs: RistrettoPoint, rhs: RistrettoPoint) -> Self {
+        Self {
+            equation,
+            passed: lhs == rhs,
+            lhs: lhs.compress().to_bytes(),
+            rhs: rhs.compress().to_bytes(),
+        }
+    }
+
+    fn scalars(equation: Equation, lhs: Scalar, rhs: Scalar) -> Self {
+        Self {
+            equation,
+            passed: lhs == rhs,
+            lhs: lhs.to_bytes(),
+            rhs: rhs.to_bytes(),
+        }
+    }
+
+    pub fn equation(&self) -> Equation {
+        self.equation
+    }
+
+    pub fn passed(&self) -> bool {
+        self.passed
+    }
+
+    pub fn lhs(&self) -> [u8; 32] {
+        self.lhs
+    }
+
+    pub fn rhs(&self) -> [u8; 32] {
+        self.rhs
+    }
+}
+
+impl VerificationReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|x| x.passed)
+    }
+
+    pub fn checks(&self) -> Vec<EquationCheck> {
+        self.checks.clone()
+    }
+
+    pub fn failures(&self) -> Vec<EquationCheck> {
+        self.checks.iter().filter(|x| !x.passed).cloned().collect()
+    }
+
+    fn result(&self) -> Result<(), ProofError> {
+        if !self.passed() {
+            return Err(ProofError::VerificationError);
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Equation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Equation::InnerProduct => write!(f, "P == aG + bH + ab(G_i)"),
+            Equation::CommitmentAS => write!(f, "A + uS - zG + (z*y^n + z^2*2^n)H' == C + pi_lr*B"),
+            Equation::CommitmentVT => write!(f, "tu*G_i + pi_t*B == z^2*V + delta*G_i + u*T1 + u^2*T2"),
+            Equation::PolynomialT => write!(f, "tu == <lu, ru>"),
+        }
+    }
+}
+
+impl fmt::Display for EquationCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.passed { "passed" } else { "failed" };
+        write!(f, "{} {}: lhs {}, rhs {}", self.equation, status, to_hex(&self.lhs), to_hex(&self.rhs))
+    }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{}", check)?;
+        }
 
-    pub fn y_inv_h(&self) -> Vec<RistrettoPoint> {
-        self.y_inv_h.clone()
+        Ok(())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bullerproof::prove_commitments_log;
+    use crate::prover::{ASVcommitment, BulletProof, Salts};
+
+    const RANGE: usize = 8;
+
+    // The interactive flow of main.rs up to the polynomial commitment.
+    fn interactive(value: u64, points: &GlobalPoints) -> (ASVcommitment, T1T2commitment, Polycommitment, Generatives) {
+        let salt = Salts::init();
+        let asv = ASVcommitment::compute(value, RANGE, &salt, points);
+        let gen = Generatives::init(RANGE, points);
+        let t1t2 = T1T2commitment::init(&salt, &asv, gen.y(), gen.z(), RANGE, points);
+        let poly = Polycommitment::compute(gen.u(), salt, &asv, &t1t2, gen.y(), gen.z(), RANGE, points);
+
+        (asv, t1t2, poly, gen)
+    }
+
+    // Folds down to single scalars, then reports on the verifier's final state for a `count`-bit key.
+    fn bullet_report(asv: [RistrettoPoint; 3], t1t2: &T1T2commitment, poly: &Polycommitment, gen: &Generatives, points: &GlobalPoints, count: usize) -> Result<VerificationReport, ProofError> {
+        let [left, right] = BulletProof::compute_diagonal([poly.lu(), poly.ru()], [points.g_basis(), poly.y_inv_h()], points.g_i());
+        let mut verifier = BulletVerify::init([left, right], asv, [t1t2.commit_t1(), t1t2.commit_t2()], poly.bullet_verifier(), points, gen.y_inv_h(), gen.z(), RANGE);
+        let mut prover = BulletProof::init(verifier.u_random(), [left, right], poly, points);
+        while prover.a().len() > 1 {
+            let [left, right] = BulletProof::compute_diagonal([prover.a(), prover.b()], [prover.g_basis(), prover.h_basis()], points.g_i());
+            verifier.u_gen();
+            let u_random = verifier.u_random();
+            verifier.compute([left, right]);
+            prover.update_diagonals([left, right]);
+            prover.compute(u_random);
+        }
+
+        verifier.report([prover.a(), prover.b()], count, points, gen)
+    }
+
+    #[test]
+    fn honest_proof_passes_every_equation() {
+        let points = GlobalPoints::gen_global(RANGE);
+        let (asv, t1t2, poly, gen) = interactive(63, &points);
+
+        let report = bullet_report(asv.to_verifier(), &t1t2, &poly, &gen, &points, RANGE).unwrap();
+        assert!(report.passed());
+        assert_eq!(report.checks().iter().map(|x| x.equation()).collect::<Vec<_>>(), [Equation::InnerProduct, Equation::CommitmentAS, Equation::CommitmentVT]);
+        assert!(report.failures().is_empty());
+
+        let report = LinearVerify::init_linear(RANGE, &points, &poly, asv.to_verifier(), &gen, &t1t2).unwrap().report();
+        assert!(report.passed());
+        assert_eq!(report.checks().len(), 3);
+    }
+
+    #[test]
+    fn wrong_commitment_fails_only_its_equation() {
+        let points = GlobalPoints::gen_global(RANGE);
+        let (asv, t1t2, poly, gen) = interactive(63, &points);
+        let [commit_a, commit_s, commit_v] = asv.to_verifier();
+        let asv = [commit_a, commit_s, commit_v + points.g_i()];
+
+        let failures = bullet_report(asv, &t1t2, &poly, &gen, &points, RANGE).unwrap().failures();
+        assert_eq!(failures.iter().map(|x| x.equation()).collect::<Vec<_>>(), [Equation::CommitmentVT]);
+        assert_ne!(failures[0].lhs(), failures[0].rhs());
+
+        let report = LinearVerify::init_linear(RANGE, &points, &poly, asv, &gen, &t1t2).unwrap().report();
+        assert_eq!(report.failures().iter().map(|x| x.equation()).collect::<Vec<_>>(), [Equation::CommitmentVT]);
+        assert!(report.to_string().contains("failed"));
+    }
+
+    #[test]
+    fn honest_rounds_verify() {
+        let points = GlobalPoints::gen_global(RANGE);
+        let (asv, t1t2, poly, gen) = interactive(200, &points);
+        let [left, right] = BulletProof::compute_diagonal([poly.lu(), poly.ru()], [points.g_basis(), poly.y_inv_h()], points.g_i());
+        let mut verifier = BulletVerify::init([left, right], asv.to_verifier(), [t1t2.commit_t1(), t1t2.commit_t2()], poly.bullet_verifier(), &points, gen.y_inv_h(), gen.z(), RANGE);
+        let mut prover = BulletProof::init(verifier.u_random(), [left, right], &poly, &points);
+
+        assert_eq!(prove_commitments_log(RANGE, &points, &mut prover, &mut verifier, &gen), Ok(()));
+    }
+
+    #[test]
+    fn failed_equation_is_an_error() {
+        let points = GlobalPoints::gen_global(RANGE);
+        let (asv, t1t2, poly, gen) = interactive(63, &points);
+        let [commit_a, commit_s, commit_v] = asv.to_verifier();
+        let asv = [commit_a, commit_s, commit_v + points.g_i()];
+        let [left, right] = BulletProof::compute_diagonal([poly.lu(), poly.ru()], [points.g_basis(), poly.y_inv_h()], points.g_i());
+        let mut verifier = BulletVerify::init([left, right], asv, [t1t2.commit_t1(), t1t2.commit_t2()], poly.bullet_verifier(), &points, gen.y_inv_h(), gen.z(), RANGE);
+        let mut prover = BulletProof::init(verifier.u_random(), [left, right], &poly, &points);
+
+        assert_eq!(prove_commitments_log(RANGE, &points, &mut prover, &mut verifier, &gen), Err(ProofError::VerificationError));
+        assert_eq!(LinearVerify::init_linear(RANGE, &points, &poly, asv, &gen, &t1t2).unwrap().verify(), Err(ProofError::VerificationError));
+    }
+
+    #[test]
+    fn rejects_short_generators() {
+        let points = GlobalPoints::gen_global(RANGE);
+        let (asv, t1t2, poly, gen) = interactive(63, &points);
+
+        assert_eq!(bullet_report(asv.to_verifier(), &t1t2, &poly, &gen, &points, 2 * RANGE).err(), Some(ProofError::InvalidGeneratorsLength));
+        assert_eq!(LinearVerify::init_linear(2 * RANGE, &points, &poly, asv.to_verifier(), &gen, &t1t2).err(), Some(ProofError::InvalidGeneratorsLength));
+    }
+
+    #[test]
+    fn key_checks_width_and_generators() {
+        let points = GlobalPoints::gen_global(RANGE);
+        let key = VerifierKey::new(6, &points).unwrap();
+
+        assert_eq!((key.range(), key.g_basis().len(), key.n2().len()), (6, RANGE, 6));
+        assert_eq!(key.g_term(Scalar::ONE), -points.g_basis().iter().sum::<RistrettoPoint>());
+        assert_eq!(VerifierKey::new(0, &points).err(), Some(ProofError::InvalidBitsize));
+        assert_eq!(VerifierKey::new(65, &points).err(), Some(ProofError::InvalidBitsize));
+        assert_eq!(VerifierKey::new(2 * RANGE, &points).err(), Some(ProofError::InvalidGeneratorsLength));
+    }
+
+    #[test]
+    fn aggregated_key_covers_every_value() {
+        let points = GlobalPoints::gen_global(32);
+        let key = VerifierKey::aggregated(&[8, 2, 16], &points).unwrap();
+        let single = VerifierKey::new(6, &points).unwrap();
+        let [y, z] = [Scalar::from(3u64), Scalar::from(5u64)];
+
+        assert_eq!((key.parties(), key.ranges(), key.range()), (3, &[8, 2, 16][..], 26));
+        assert_eq!((key.g_basis().len(), key.n2().len()), (32, 26));
+        assert_eq!(key.n2_sums(), &[Scalar::from(255u64), Scalar::from(3u64), Scalar::from(65535u64)]);
+        assert_eq!(key.delta(y, z), ((z - (z * z)) * y) - (z * z * z * Scalar::from(255u64)) - (z * z * z * z * Scalar::from(3u64)) - (z * z * z * z * z * Scalar::from(65535u64)));
+        assert_eq!(single.delta(y, z), ((z - (z * z)) * y) - (z * z * z * single.n2_sum()));
+        assert_eq!(VerifierKey::aggregated(&[], &points).err(), Some(ProofError::InvalidAggregation));
+        assert_eq!(VerifierKey::aggregated(&[8, 0], &points).err(), Some(ProofError::InvalidBitsize));
+        assert_eq!(VerifierKey::aggregated(&[32, 32], &points).err(), Some(ProofError::InvalidGeneratorsLength));
+    }
+}